@@ -0,0 +1,185 @@
+use super::*;
+
+/// Atoms own their signals as if they were one component that is never
+/// mounted, so subscriptions and propagation treat them like any state.
+pub(crate) const ATOM_COMPONENT_ID: usize = usize::MAX;
+
+/// A state shared by every component that reads it, declared once as a
+/// `static`:
+///
+/// ```ignore
+/// static COUNT: Atom<i32> = Atom::new(|| 0);
+///
+/// let (count, set_count) = ctx.atom(&COUNT);
+/// ```
+///
+/// The declaration only says how to make the first value. Each runtime
+/// keeps its own value, made the first time a component reads the atom, so
+/// runtimes side by side don't see each other's updates.
+pub struct Atom<T> {
+    init: fn() -> T,
+}
+
+impl<T> Atom<T> {
+    pub const fn new(init: fn() -> T) -> Self {
+        Self { init }
+    }
+
+    /// Statics never move, so the address tells atoms apart.
+    fn key(&'static self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+/// The value of every atom a runtime's components have read, by atom key.
+pub(crate) type AtomStore = HashMap<usize, Rc<dyn ValueCell>>;
+
+impl RuntimeState {
+    pub(crate) fn atom_state(&self, key: usize) -> Result<Rc<dyn ValueCell>, RuactError> {
+        self.atoms
+            .borrow()
+            .get(&key)
+            .cloned()
+            .ok_or(RuactError::StaleSignal {
+                component_id: ATOM_COMPONENT_ID,
+                signal_index: key,
+            })
+    }
+}
+
+pub(crate) fn handle_atom<T: Send + Sync + Debug + 'static>(
+    ctx: &Context,
+    atom: &'static Atom<T>,
+) -> (Signal<T>, SetState<T>) {
+    if !ctx.check_hook(HookSlot::new::<T>(HookKind::Atom)) {
+        return (ctx.detached_signal((atom.init)()), ctx.detached_set_state());
+    }
+    let key = atom.key();
+    let state = {
+        let mut atoms = ctx.runtime.atoms.borrow_mut();
+        let state = atoms
+            .entry(key)
+            .or_insert_with(|| Rc::new(RefCell::new((atom.init)())));
+        downcast_value_cell::<T>(state)
+    };
+    let signal_id = SignalId {
+        component_id: ATOM_COMPONENT_ID,
+        signal_index: key,
+    };
+
+    let set_state = SetState::new(ctx.runtime.tx.clone(), signal_id, key);
+    let signal = Signal::new(state, signal_id, ctx.runtime.used_signal_ids.clone());
+
+    (signal, set_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static COUNT: Atom<i32> = Atom::new(|| 0);
+
+    #[derive(Debug)]
+    struct App;
+
+    impl StaticType for App {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<App>()
+        }
+    }
+
+    impl Component for App {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            ctx.render(|| {
+                log("app");
+                (Counter, Doubled, Unrelated)
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct Counter;
+
+    impl StaticType for Counter {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Counter>()
+        }
+    }
+
+    impl Component for Counter {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (count, set_count) = ctx.atom(&COUNT);
+            ctx.render_with_event(
+                |_: &()| set_count.mutate(|count| *count += 1),
+                |ctx| {
+                    log(format!("counter {}", *count.get()));
+                    Native::Button {
+                        label: format!("Count {}", *count.get()),
+                        on_click: ctx.event(()),
+                    }
+                },
+            )
+        }
+    }
+
+    #[derive(Debug)]
+    struct Doubled;
+
+    impl StaticType for Doubled {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Doubled>()
+        }
+    }
+
+    impl Component for Doubled {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (count, _) = ctx.atom(&COUNT);
+            let doubled = ctx.memo(|| *count.get() * 2);
+            ctx.effect("log doubled", || log(format!("effect {}", *doubled.get())));
+            let text = ctx.memo(|| format!("Doubled {}", *doubled.get()));
+            ctx.render(|| Native::Text { text })
+        }
+    }
+
+    #[derive(Debug)]
+    struct Unrelated;
+
+    impl StaticType for Unrelated {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Unrelated>()
+        }
+    }
+
+    impl Component for Unrelated {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let text = ctx.memo(|| "Unrelated".to_string());
+            ctx.render(|| {
+                log("unrelated");
+                Native::Text { text }
+            })
+        }
+    }
+
+    #[test]
+    fn update_reaches_every_render_memo_and_effect_that_read_the_atom() {
+        let mut renderer = TestRenderer::new(App);
+        assert_eq!(take_log(), ["app", "counter 0", "effect 0", "unrelated"]);
+
+        renderer.click(&renderer.get_by_text("Count 0"));
+
+        assert_eq!(take_log(), ["counter 1", "effect 2"]);
+        renderer.get_by_text("Count 1");
+        renderer.get_by_text("Doubled 2");
+    }
+
+    #[test]
+    fn each_runtime_has_its_own_value() {
+        let mut first = TestRenderer::new(Counter);
+        let second = TestRenderer::new(Counter);
+
+        first.click(&first.get_by_text("Count 0"));
+
+        first.get_by_text("Count 1");
+        second.get_by_text("Count 0");
+    }
+}
@@ -1,22 +1,26 @@
 use super::*;
-use std::sync::OnceLock;
 
-pub(crate) static RX: OnceLock<crossbeam::channel::Receiver<Item>> = OnceLock::new();
-pub(crate) static TX: OnceLock<crossbeam::channel::Sender<Item>> = OnceLock::new();
+/// The sending side of a runtime's queue. Set state and event handles keep
+/// one, so they reach the runtime that made them from any thread.
+pub(crate) type Sender = crossbeam::channel::Sender<Item>;
+pub(crate) type Receiver = crossbeam::channel::Receiver<Item>;
 
 #[derive(Debug)]
 pub(crate) enum Item {
     SetStateItem(SetStateItem),
-    EventCallback(EventCallback),
+    Event {
+        event_callback: EventCallback,
+        payload: Box<dyn Any + Send>,
+    },
+    NativeEvent {
+        node_id: usize,
+        name: &'static str,
+        payload: Box<dyn Any + Send>,
+    },
 }
 
-pub(crate) fn init() {
-    let (tx, rx) = crossbeam::channel::unbounded();
-    RX.set(rx).unwrap();
-    TX.set(tx).unwrap();
-}
-
-pub(crate) fn send(item: Item) {
-    println!("Channel Send: {:#?}", item);
-    TX.get().unwrap().send(item).unwrap();
+/// Queues an item. Once the runtime is gone, nothing is listening anymore
+/// and the item is dropped.
+pub(crate) fn send(tx: &Sender, item: Item) {
+    let _ = tx.send(item);
 }
@@ -0,0 +1,343 @@
+use super::*;
+
+/// One rendered child, with the key it was given via `keyed` and the test
+/// id it was given via `test_id`, if any.
+pub struct Child {
+    pub(crate) key: Option<Key>,
+    pub(crate) test_id: Option<String>,
+    pub(crate) component: Box<dyn Component>,
+}
+
+impl Debug for Child {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Child")
+            .field("key", &self.key)
+            .field("test_id", &self.test_id)
+            .field("component", &self.component)
+            .finish()
+    }
+}
+
+/// Identifies a child among its siblings across renders. Keys only need to
+/// be unique within one parent.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Int(i64),
+    Str(String),
+}
+
+macro_rules! impl_key_from_int {
+    ($($int:ty),+) => {
+        $(impl From<$int> for Key {
+            fn from(value: $int) -> Self {
+                Key::Int(value as i64)
+            }
+        })+
+    };
+}
+
+impl_key_from_int!(i32, i64, u32, u64, usize);
+
+impl From<&str> for Key {
+    fn from(value: &str) -> Self {
+        Key::Str(value.to_string())
+    }
+}
+
+impl From<String> for Key {
+    fn from(value: String) -> Self {
+        Key::Str(value)
+    }
+}
+
+/// A component tagged with a key. Keyed children are matched to the previous
+/// render by key instead of by position, so they keep their state when the
+/// list is reordered.
+#[derive(Debug)]
+pub struct Keyed<C> {
+    key: Key,
+    component: C,
+}
+
+pub fn keyed<C: Component>(key: impl Into<Key>, component: C) -> Keyed<C> {
+    Keyed {
+        key: key.into(),
+        component,
+    }
+}
+
+/// Anything `Context::render` can return: a single component, a tuple of
+/// them, a `Vec`, an `Option` or a `Fragment` collected from an iterator.
+///
+/// Children outlive the render that created them, so they have to own their
+/// props (`'static`). To hand a parent's value down, pass its `Signal`.
+pub trait IntoChildren {
+    fn push_children(self, children: &mut Vec<Child>);
+
+    fn into_children(self) -> Vec<Child>
+    where
+        Self: Sized,
+    {
+        let mut children = vec![];
+        self.push_children(&mut children);
+        children
+    }
+}
+
+impl<C: Component + 'static> IntoChildren for C {
+    fn push_children(self, children: &mut Vec<Child>) {
+        children.push(Child {
+            key: None,
+            test_id: None,
+            component: Box::new(self),
+        });
+    }
+}
+
+impl IntoChildren for Box<dyn Component> {
+    fn push_children(self, children: &mut Vec<Child>) {
+        children.push(Child {
+            key: None,
+            test_id: None,
+            component: self,
+        });
+    }
+}
+
+/// A native tagged with an id that tests can find it by. Backends get it
+/// as `Prop::TestId`.
+#[derive(Debug)]
+pub struct WithTestId {
+    test_id: String,
+    native: Native,
+}
+
+pub fn test_id(test_id: impl Into<String>, native: Native) -> WithTestId {
+    WithTestId {
+        test_id: test_id.into(),
+        native,
+    }
+}
+
+impl IntoChildren for WithTestId {
+    fn push_children(self, children: &mut Vec<Child>) {
+        children.push(Child {
+            key: None,
+            test_id: Some(self.test_id),
+            component: Box::new(self.native),
+        });
+    }
+}
+
+impl IntoChildren for Child {
+    fn push_children(self, children: &mut Vec<Child>) {
+        children.push(self);
+    }
+}
+
+impl<C: Component + 'static> IntoChildren for Keyed<C> {
+    fn push_children(self, children: &mut Vec<Child>) {
+        children.push(Child {
+            key: Some(self.key),
+            test_id: None,
+            component: Box::new(self.component),
+        });
+    }
+}
+
+impl<T: IntoChildren> IntoChildren for Vec<T> {
+    fn push_children(self, children: &mut Vec<Child>) {
+        for child in self {
+            child.push_children(children);
+        }
+    }
+}
+
+/// A `None` child still takes its place among the siblings, see
+/// `Placeholder`.
+impl<T: IntoChildren> IntoChildren for Option<T> {
+    fn push_children(self, children: &mut Vec<Child>) {
+        match self {
+            Some(child) => child.push_children(children),
+            None => Placeholder.push_children(children),
+        }
+    }
+}
+
+/// Rendered in place of a `None` child. Unkeyed children are matched to the
+/// previous render by position, so without it the siblings after an
+/// optional child would shift onto each other's holders when it toggles.
+#[derive(Debug)]
+pub(crate) struct Placeholder;
+
+impl StaticType for Placeholder {
+    fn static_type_id(&self) -> TypeId {
+        TypeId::of::<Placeholder>()
+    }
+}
+
+impl Component for Placeholder {
+    fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+        ctx.render(Fragment::new)
+    }
+}
+
+impl IntoChildren for () {
+    fn push_children(self, _children: &mut Vec<Child>) {}
+}
+
+macro_rules! impl_into_children_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: IntoChildren),+> IntoChildren for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn push_children(self, children: &mut Vec<Child>) {
+                let ($($name,)+) = self;
+                $($name.push_children(children);)+
+            }
+        }
+    };
+}
+
+impl_into_children_for_tuple!(T0);
+impl_into_children_for_tuple!(T0, T1);
+impl_into_children_for_tuple!(T0, T1, T2);
+impl_into_children_for_tuple!(T0, T1, T2, T3);
+impl_into_children_for_tuple!(T0, T1, T2, T3, T4);
+impl_into_children_for_tuple!(T0, T1, T2, T3, T4, T5);
+impl_into_children_for_tuple!(T0, T1, T2, T3, T4, T5, T6);
+impl_into_children_for_tuple!(T0, T1, T2, T3, T4, T5, T6, T7);
+impl_into_children_for_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8);
+impl_into_children_for_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_into_children_for_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_into_children_for_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+
+/// A flat list of siblings, usually built with `collect()`:
+///
+/// ```ignore
+/// ctx.render(|| items.iter().map(|item| Item { item }).collect::<Fragment>())
+/// ```
+pub struct Fragment {
+    children: Vec<Child>,
+}
+
+impl Fragment {
+    pub fn new() -> Self {
+        Self { children: vec![] }
+    }
+
+    pub fn push(&mut self, child: impl IntoChildren) {
+        child.push_children(&mut self.children);
+    }
+}
+
+impl Default for Fragment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: IntoChildren> FromIterator<T> for Fragment {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut fragment = Fragment::new();
+        for child in iter {
+            fragment.push(child);
+        }
+        fragment
+    }
+}
+
+impl IntoChildren for Fragment {
+    fn push_children(self, children: &mut Vec<Child>) {
+        children.extend(self.children);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Gallery;
+
+    impl StaticType for Gallery {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Gallery>()
+        }
+    }
+
+    impl Component for Gallery {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (names, set_names) = ctx.state(|| vec!["a", "b", "c"]);
+            ctx.render_with_event(
+                |_: &()| set_names.mutate(|names| names.reverse()),
+                |ctx| {
+                    let photos = names
+                        .get()
+                        .iter()
+                        .map(|&name| Photo { name })
+                        .collect::<Fragment>();
+                    let footer: Vec<Box<dyn Component>> = vec![
+                        Box::new(Photo { name: "logo" }),
+                        Box::new(Native::Image {
+                            src: "footer.png".to_string(),
+                        }),
+                    ];
+                    (
+                        Native::Button {
+                            label: "Reverse".to_string(),
+                            on_click: ctx.event(()),
+                        },
+                        photos,
+                        footer,
+                    )
+                },
+            )
+        }
+    }
+
+    #[derive(Debug)]
+    struct Photo {
+        name: &'static str,
+    }
+
+    impl StaticType for Photo {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Photo>()
+        }
+    }
+
+    impl Component for Photo {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            ctx.render(|| Native::Image {
+                src: format!("{}.png", self.name),
+            })
+        }
+    }
+
+    fn image_srcs(renderer: &TestRenderer) -> Vec<String> {
+        renderer
+            .find_all_by_kind(NativeKind::Image)
+            .into_iter()
+            .map(|image| match image.prop("src") {
+                Some(Prop::Src(src)) => src.clone(),
+                prop => panic!("image without a src: {:?}", prop),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn siblings_from_iterators_mount_in_order() {
+        let mut renderer = TestRenderer::new(Gallery);
+        assert_eq!(
+            image_srcs(&renderer),
+            ["a.png", "b.png", "c.png", "logo.png", "footer.png"]
+        );
+
+        renderer.click(&renderer.get_by_text("Reverse"));
+
+        assert_eq!(
+            image_srcs(&renderer),
+            ["c.png", "b.png", "a.png", "logo.png", "footer.png"]
+        );
+    }
+}
@@ -1,28 +1,186 @@
 use super::*;
 
-pub(crate) fn handle_effect<'a>(ctx: &'a Context, effect: impl FnOnce()) {
-    unsafe {
-        let effect_used_signals_list = ctx
-            .instance
-            .effect_used_signals_list
-            .as_ptr()
-            .as_mut()
-            .unwrap();
-        let effect_index = ctx
-            .effect_index
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-
-        let is_first_run = || effect_used_signals_list.len() <= effect_index;
-
-        let used_signal_updated = || {
-            let used_signals = effect_used_signals_list.get(effect_index).unwrap();
-            ctx.is_used_signal_updated(used_signals)
+pub(crate) type CleanupFn = Box<dyn FnOnce() + Send>;
+
+/// What an effect may return: nothing, or a closure that undoes the effect.
+/// The cleanup runs right before the effect runs again and when the
+/// component unmounts.
+pub trait EffectCleanup {
+    fn into_cleanup(self) -> Option<CleanupFn>;
+}
+
+impl EffectCleanup for () {
+    fn into_cleanup(self) -> Option<CleanupFn> {
+        None
+    }
+}
+
+impl<F: FnOnce() + Send + 'static> EffectCleanup for F {
+    fn into_cleanup(self) -> Option<CleanupFn> {
+        Some(Box::new(self))
+    }
+}
+
+pub(crate) fn handle_effect<Cleanup: EffectCleanup + 'static>(
+    ctx: &Context,
+    name: &'static str,
+    effect: impl FnOnce() -> Cleanup,
+) {
+    if !ctx.check_hook(HookSlot::new::<Cleanup>(HookKind::Effect { name })) {
+        return;
+    }
+    run_effect(ctx, effect);
+}
+
+/// Runs an effect in the next effect slot if it is new or a signal it read
+/// last time changed, after cleaning up its previous run.
+pub(crate) fn run_effect<Cleanup: EffectCleanup + 'static>(
+    ctx: &Context,
+    effect: impl FnOnce() -> Cleanup,
+) {
+    let slot_index = next_index(&ctx.slot_index);
+
+    let is_first_run = || ctx.instance.slots.borrow().is_vacant(slot_index);
+
+    let used_signal_updated = || {
+        let mut slots = ctx.instance.slots.borrow_mut();
+        let (used_signals, _) = slots.effect_mut(slot_index);
+        ctx.is_used_signal_updated(used_signals.iter())
+    };
+
+    if is_first_run() || ctx.is_set_state_phase() && used_signal_updated() {
+        let is_first_run = is_first_run();
+        if !is_first_run {
+            let prev_cleanup = ctx
+                .instance
+                .slots
+                .borrow_mut()
+                .effect_mut(slot_index)
+                .1
+                .take();
+            if let Some(cleanup) = prev_cleanup {
+                cleanup();
+            }
+        }
+        let cleanup = effect().into_cleanup();
+        let subscriber = Subscriber {
+            component_id: ctx.instance.component_id,
+            hook: TrackedHook::Effect { slot_index },
         };
+        let mut slots = ctx.instance.slots.borrow_mut();
+        if is_first_run {
+            let mut used_signals = Vec::new();
+            ctx.runtime.take_used_signals(subscriber, &mut used_signals);
+            slots.claim(
+                slot_index,
+                Slot::Effect {
+                    used_signals,
+                    cleanup,
+                },
+            );
+        } else {
+            let (used_signals, prev_cleanup) = slots.effect_mut(slot_index);
+            ctx.runtime.take_used_signals(subscriber, used_signals);
+            *prev_cleanup = cleanup;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if is_first_run() || ctx.is_set_state_phase() && used_signal_updated() {
-            effect();
-            let used_signal_ids = take_used_signals();
-            update_or_push(effect_used_signals_list, effect_index, used_signal_ids);
+    /// Joins its topic for as long as it is mounted, and so does each member
+    /// for itself.
+    #[derive(Debug)]
+    struct Room;
+
+    impl StaticType for Room {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Room>()
         }
     }
+
+    impl Component for Room {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (topic, _) = ctx.state(|| "a");
+            let (members, _) = ctx.state(|| vec![1, 2, 3]);
+            ctx.effect("join topic", || {
+                let topic = *topic.get();
+                log(format!("join {topic}"));
+                move || log(format!("leave {topic}"))
+            });
+            ctx.render(|| {
+                members
+                    .get()
+                    .iter()
+                    .map(|&id| keyed(id, Member { id }))
+                    .collect::<Vec<_>>()
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct Member {
+        id: i32,
+    }
+
+    impl StaticType for Member {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Member>()
+        }
+    }
+
+    impl Component for Member {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let id = self.id;
+            ctx.effect("join room", || move || log(format!("member {id} leaves")));
+            ctx.render(|| ())
+        }
+    }
+
+    #[test]
+    fn cleanup_runs_before_the_effect_runs_again() {
+        let (runtime, root_holder) = mount(Room);
+        assert_eq!(take_log(), ["join a"]);
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, "b")],
+        );
+
+        assert_eq!(take_log(), ["leave a", "join b"]);
+    }
+
+    #[test]
+    fn removed_children_run_their_cleanups() {
+        let (runtime, root_holder) = mount(Room);
+        take_log();
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 1, vec![2])],
+        );
+        assert_eq!(take_log(), ["member 1 leaves", "member 3 leaves"]);
+    }
+
+    #[test]
+    fn children_clean_up_before_their_parent() {
+        let (runtime, root_holder) = mount(Room);
+        take_log();
+
+        unmount_visit(&runtime, root_holder);
+
+        assert_eq!(
+            take_log(),
+            [
+                "member 1 leaves",
+                "member 2 leaves",
+                "member 3 leaves",
+                "leave a"
+            ]
+        );
+    }
 }
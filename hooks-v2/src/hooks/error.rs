@@ -0,0 +1,227 @@
+use super::*;
+use std::rc::Rc;
+
+pub(crate) type ErrorReporter = Rc<dyn Fn(&RuactError)>;
+
+/// Something the runtime had to drop instead of handling. None of these stop
+/// the event loop; they go to the error reporter, see
+/// `Runtime::set_error_reporter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuactError {
+    /// A state update or event was addressed to a component that is not
+    /// mounted (anymore).
+    ComponentUnmounted { component_id: usize },
+    /// A state update names a state the component doesn't have.
+    StaleSignal {
+        component_id: usize,
+        signal_index: usize,
+    },
+    /// A state update carries a value of another type than the state.
+    StateTypeMismatch {
+        component_type_name: &'static str,
+        state_index: usize,
+        state_type_name: &'static str,
+        update_type_name: &'static str,
+    },
+    /// An event reached a component whose `render_with_event` takes another
+    /// event type.
+    EventTypeMismatch {
+        component_type_name: &'static str,
+        expected_type_name: &'static str,
+    },
+    /// An event made by `event_with` was fired with a payload of another
+    /// type than it takes.
+    EventPayloadMismatch {
+        component_type_name: &'static str,
+        expected_type_name: &'static str,
+    },
+    /// An event reached a component that renders with `render` and so has
+    /// no event handler.
+    EventNotHandled { component_type_name: &'static str },
+    /// A component returned children while handling an event. Event handlers
+    /// can only update state; the children are dropped.
+    RenderedDuringEvent { component_type_name: &'static str },
+    /// A component called its hooks in a different order, or a different
+    /// number of them, than on its first render. Its render is skipped for
+    /// that pass and its hooks keep what they had.
+    HookOrder {
+        component_type_name: &'static str,
+        hook_index: usize,
+        /// The hook at `hook_index` on the first render.
+        expected: Option<String>,
+        /// The hook at `hook_index` this time.
+        found: Option<String>,
+    },
+    /// A component asked for an interval with a zero period, which would
+    /// fire without end. The interval is not set.
+    ZeroInterval { component_type_name: &'static str },
+    /// A component rendered two children with the same key. The later ones
+    /// are matched across renders as if they had no key.
+    DuplicateKey {
+        component_type_name: &'static str,
+        key: Key,
+    },
+}
+
+impl std::fmt::Display for RuactError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuactError::ComponentUnmounted { component_id } => {
+                write!(f, "component {} is unmounted", component_id)
+            }
+            RuactError::StaleSignal {
+                component_id,
+                signal_index,
+            } => write!(
+                f,
+                "component {} has no state for signal {}",
+                component_id, signal_index
+            ),
+            RuactError::StateTypeMismatch {
+                component_type_name,
+                state_index,
+                state_type_name,
+                update_type_name,
+            } => write!(
+                f,
+                "{}: state {} is a {} but the update is for a {}",
+                component_type_name, state_index, state_type_name, update_type_name
+            ),
+            RuactError::EventTypeMismatch {
+                component_type_name,
+                expected_type_name,
+            } => write!(
+                f,
+                "{}: event is not a {}",
+                component_type_name, expected_type_name
+            ),
+            RuactError::EventPayloadMismatch {
+                component_type_name,
+                expected_type_name,
+            } => write!(
+                f,
+                "{}: event payload is not a {}",
+                component_type_name, expected_type_name
+            ),
+            RuactError::EventNotHandled {
+                component_type_name,
+            } => write!(
+                f,
+                "{}: got an event but doesn't render with an event handler",
+                component_type_name
+            ),
+            RuactError::RenderedDuringEvent {
+                component_type_name,
+            } => write!(
+                f,
+                "{}: rendered while handling an event",
+                component_type_name
+            ),
+            RuactError::HookOrder {
+                component_type_name,
+                hook_index,
+                expected,
+                found,
+            } => {
+                write!(f, "{}: hook {} ", component_type_name, hook_index)?;
+                match (expected, found) {
+                    (Some(expected), Some(found)) => write!(
+                        f,
+                        "was {} on the first render but is {} now",
+                        expected, found
+                    )?,
+                    (None, Some(found)) => {
+                        write!(f, "({}) was not called on the first render", found)?
+                    }
+                    (Some(expected), None) => {
+                        write!(f, "({}) was not called this render", expected)?
+                    }
+                    (None, None) => unreachable!(),
+                }
+                write!(
+                    f,
+                    "; hooks must be called unconditionally and in the same order on every render"
+                )
+            }
+            RuactError::ZeroInterval {
+                component_type_name,
+            } => write!(f, "{}: interval period is zero", component_type_name),
+            RuactError::DuplicateKey {
+                component_type_name,
+                key,
+            } => write!(
+                f,
+                "{}: rendered more than one child with key {:?}",
+                component_type_name, key
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuactError {}
+
+pub(crate) fn default_error_reporter() -> ErrorReporter {
+    Rc::new(|error| eprintln!("ruact: {}", error))
+}
+
+impl RuntimeState {
+    pub(crate) fn report_error(&self, error: RuactError) {
+        let reporter = self.error_reporter.borrow().clone();
+        reporter(&error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_items_are_reported_and_the_rest_of_the_batch_runs() {
+        let (runtime, root_holder) = mount(Adder);
+        let component_id = root_holder.component_instance.component_id;
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![
+                event_item(&runtime, component_id, "not an i32"),
+                event_item(&runtime, usize::MAX, 1),
+                set_state(&root_holder, 1, 0),
+                set_state(&root_holder, 0, "not an i32"),
+                set_state(&root_holder, 0, 5),
+            ],
+        );
+
+        let component_type_name = std::any::type_name::<Adder>();
+        assert_eq!(
+            take_reported(),
+            [
+                RuactError::EventTypeMismatch {
+                    component_type_name,
+                    expected_type_name: "i32",
+                },
+                RuactError::ComponentUnmounted {
+                    component_id: usize::MAX
+                },
+                RuactError::StaleSignal {
+                    component_id,
+                    signal_index: 1,
+                },
+                RuactError::StateTypeMismatch {
+                    component_type_name,
+                    state_index: 0,
+                    state_type_name: "i32",
+                    update_type_name: "&str",
+                },
+            ]
+        );
+        let count = root_holder
+            .component_instance
+            .slots
+            .borrow()
+            .state(0)
+            .unwrap()
+            .clone();
+        assert_eq!(format!("{:?}", count), "5");
+    }
+}
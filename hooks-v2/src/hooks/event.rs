@@ -1,15 +1,100 @@
 use super::*;
 
-#[derive(Clone, Debug)]
+pub(crate) type EventValue = Arc<dyn Any + Send + Sync>;
+type MapPayload = Arc<dyn Fn(Box<dyn Any + Send>) -> Option<EventValue> + Send + Sync>;
+
+/// Sends an event to the component that rendered it, on the runtime that
+/// rendered it. Backends call it when the user interacts with a node,
+/// passing what they know about the interaction as the payload.
+#[derive(Clone)]
 pub struct EventCallback {
+    pub(crate) tx: channel::Sender,
     pub(crate) component_id: usize,
-    pub(crate) event: Arc<dyn Any + Send + Sync>,
+    pub(crate) source: EventSource,
+}
+
+#[derive(Clone)]
+pub(crate) enum EventSource {
+    /// From `EventContext::event`; any payload is ignored.
+    Event(EventValue),
+    /// From `EventContext::event_with`; the event is made from the payload
+    /// when it is dispatched.
+    Map {
+        payload_type_name: &'static str,
+        map: MapPayload,
+    },
+}
+
+impl EventSource {
+    pub(crate) fn map<Payload: Any + Send, Event: Any + Send + Sync>(
+        map: impl Fn(Payload) -> Event + Send + Sync + 'static,
+    ) -> Self {
+        EventSource::Map {
+            payload_type_name: std::any::type_name::<Payload>(),
+            map: Arc::new(move |payload: Box<dyn Any + Send>| {
+                let payload = payload.downcast::<Payload>().ok()?;
+                Some(Arc::new(map(*payload)) as EventValue)
+            }),
+        }
+    }
 }
-unsafe impl Send for EventCallback {}
-unsafe impl Sync for EventCallback {}
 
 impl EventCallback {
-    pub(crate) fn call(&self) {
-        channel::send(channel::Item::EventCallback(self.clone()));
+    /// Fires the event without a payload.
+    pub fn call(&self) {
+        self.call_with(());
+    }
+
+    /// Fires the event with the data of this interaction. Callbacks made by
+    /// `event_with` turn it into the event once it is dispatched.
+    pub fn call_with<Payload: Any + Send>(&self, payload: Payload) {
+        channel::send(
+            &self.tx,
+            Item::Event {
+                event_callback: self.clone(),
+                payload: Box::new(payload),
+            },
+        );
+    }
+
+    /// The event for a payload, or the payload type that was expected if the
+    /// payload is of another type.
+    pub(crate) fn resolve(&self, payload: Box<dyn Any + Send>) -> Result<EventValue, &'static str> {
+        match &self.source {
+            EventSource::Event(event) => Ok(event.clone()),
+            EventSource::Map {
+                payload_type_name,
+                map,
+            } => map(payload).ok_or(*payload_type_name),
+        }
+    }
+}
+
+impl Debug for EventCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("EventCallback");
+        debug.field("component_id", &self.component_id);
+        match &self.source {
+            EventSource::Event(event) => debug.field("event", event),
+            EventSource::Map {
+                payload_type_name, ..
+            } => debug.field("payload", payload_type_name),
+        };
+        debug.finish()
+    }
+}
+
+/// Two callbacks are equal if they are the same callback, so a re-rendered
+/// callback counts as changed.
+impl PartialEq for EventCallback {
+    fn eq(&self, other: &Self) -> bool {
+        self.component_id == other.component_id
+            && match (&self.source, &other.source) {
+                (EventSource::Event(event), EventSource::Event(other)) => Arc::ptr_eq(event, other),
+                (EventSource::Map { map, .. }, EventSource::Map { map: other, .. }) => {
+                    Arc::ptr_eq(map, other)
+                }
+                _ => false,
+            }
     }
 }
@@ -0,0 +1,215 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HookKind {
+    State,
+    Reducer,
+    Atom,
+    Memo,
+    Effect { name: &'static str },
+    Task { name: &'static str },
+    Timer,
+    Provide,
+    UseContext,
+    Listener { phase: Phase },
+}
+
+/// What the hook at one index was on the component's first render. Every
+/// later render must call the same hooks in the same order, which is what
+/// lets storage be indexed by call order.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct HookSlot {
+    pub(crate) kind: HookKind,
+    pub(crate) type_id: TypeId,
+    pub(crate) type_name: &'static str,
+}
+
+impl HookSlot {
+    pub(crate) fn new<T: 'static>(kind: HookKind) -> Self {
+        Self {
+            kind,
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+}
+
+impl PartialEq for HookSlot {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.type_id == other.type_id
+    }
+}
+
+impl std::fmt::Display for HookSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            HookKind::State => write!(f, "state<{}>", self.type_name),
+            HookKind::Reducer => write!(f, "reducer<{}>", self.type_name),
+            HookKind::Memo => write!(f, "memo<{}>", self.type_name),
+            HookKind::Effect { name } => write!(f, "effect {:?}", name),
+            HookKind::Task { name } => write!(f, "task {:?}<{}>", name, self.type_name),
+            HookKind::Timer => write!(f, "timer<{}>", self.type_name),
+            HookKind::Atom => write!(f, "atom<{}>", self.type_name),
+            HookKind::Provide => write!(f, "provide<{}>", self.type_name),
+            HookKind::UseContext => write!(f, "use_context<{}>", self.type_name),
+            HookKind::Listener { phase } => write!(f, "{:?} listener", phase),
+        }
+    }
+}
+
+impl Context {
+    /// Checks the next hook against its slot, recording the slot on the
+    /// first render. Must run before the hook touches its storage.
+    ///
+    /// A mismatch is reported as `RuactError::HookOrder`, and from then on
+    /// this returns false for every hook of the run: the hook leaves its
+    /// storage alone and hands out a stand-in, see `detached_signal`, and
+    /// the render is skipped for this pass.
+    #[must_use]
+    pub(crate) fn check_hook(&self, slot: HookSlot) -> bool {
+        if self.is_hook_order_broken.get() {
+            return false;
+        }
+        let hook_index = next_index(&self.hook_index);
+        let mut hook_slots = self.instance.hook_slots.borrow_mut();
+        let expected = hook_slots.get(hook_index).copied();
+        match expected {
+            Some(expected) if expected == slot => true,
+            None if matches!(self.context_for, ContextFor::Mount) => {
+                hook_slots.push(slot);
+                true
+            }
+            expected => {
+                drop(hook_slots);
+                self.report_hook_order(hook_index, expected, Some(slot));
+                false
+            }
+        }
+    }
+
+    /// Checks that the component called all of its hooks, once it is about
+    /// to render. False if it didn't or an earlier hook was out of order.
+    pub(crate) fn check_hook_count(&self) -> bool {
+        if self.is_hook_order_broken.get() {
+            return false;
+        }
+        let hook_index = self.hook_index.get();
+        let expected = self.instance.hook_slots.borrow().get(hook_index).copied();
+        match expected {
+            Some(expected) => {
+                self.report_hook_order(hook_index, Some(expected), None);
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn report_hook_order(
+        &self,
+        hook_index: usize,
+        expected: Option<HookSlot>,
+        found: Option<HookSlot>,
+    ) {
+        self.is_hook_order_broken.set(true);
+        self.runtime.report_error(RuactError::HookOrder {
+            component_type_name: self.instance.component_type_name,
+            hook_index,
+            expected: expected.map(|slot| slot.to_string()),
+            found: found.map(|slot| slot.to_string()),
+        });
+    }
+
+    /// Stands in for the signal of a hook that can't reach its storage. The
+    /// render of the run is skipped, so nothing stays subscribed to it.
+    pub(crate) fn detached_signal<T>(&self, value: T) -> Signal<T> {
+        Signal::new(
+            Rc::new(RefCell::new(value)),
+            self.detached_signal_id(),
+            self.runtime.used_signal_ids.clone(),
+        )
+    }
+
+    /// Stands in for the `SetState` of a hook that can't reach its storage.
+    /// It names no state, so an update sent with it is reported as stale.
+    pub(crate) fn detached_set_state<T: Send + Sync + Debug + 'static>(&self) -> SetState<T> {
+        let signal_id = self.detached_signal_id();
+        SetState::new(self.runtime.tx.clone(), signal_id, signal_id.signal_index)
+    }
+
+    fn detached_signal_id(&self) -> SignalId {
+        SignalId {
+            component_id: self.instance.component_id,
+            signal_index: usize::MAX,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Conditional;
+
+    impl StaticType for Conditional {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Conditional>()
+        }
+    }
+
+    impl Component for Conditional {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (flag, _) = ctx.state(|| false);
+            if *flag.get() {
+                let _ = ctx.memo(|| 0);
+            }
+            ctx.effect("log flag", || log(format!("effect {}", *flag.get())));
+            ctx.render(|| log(format!("render {}", *flag.get())))
+        }
+    }
+
+    #[test]
+    fn conditional_hook_is_reported_and_skips_the_render() {
+        let (runtime, root_holder) = mount(Conditional);
+        assert_eq!(take_log(), ["effect false", "render false"]);
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, true)],
+        );
+
+        assert_eq!(
+            take_reported(),
+            [RuactError::HookOrder {
+                component_type_name: std::any::type_name::<Conditional>(),
+                hook_index: 1,
+                expected: Some("effect \"log flag\"".to_string()),
+                found: Some("memo<i32>".to_string()),
+            }]
+        );
+        assert_eq!(take_log(), Vec::<String>::new());
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, false)],
+        );
+
+        assert_eq!(take_log(), ["effect false", "render false"]);
+        assert_eq!(take_reported(), []);
+    }
+
+    #[test]
+    fn hook_order_error_names_missing_hook() {
+        let error = RuactError::HookOrder {
+            component_type_name: "Conditional",
+            hook_index: 2,
+            expected: Some(HookSlot::new::<()>(HookKind::Effect { name: "log flag" }).to_string()),
+            found: None,
+        };
+        assert!(error
+            .to_string()
+            .starts_with("Conditional: hook 2 (effect \"log flag\") was not called this render"));
+    }
+}
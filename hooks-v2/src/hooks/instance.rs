@@ -1,59 +1,68 @@
 use super::*;
-use std::{any::TypeId, fmt::Debug, sync::atomic::AtomicBool};
+use std::{
+    any::TypeId,
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    rc::Rc,
+};
 
 pub(crate) struct ComponentInstance {
     pub(crate) component_id: usize,
+    pub(crate) parent_id: Option<usize>,
     pub(crate) component_type_id: TypeId,
     pub(crate) component_type_name: &'static str,
-    pub(crate) state_list: AtomicCell<Vec<Arc<dyn Value>>>,
-    pub(crate) effect_used_signals_list: AtomicCell<Vec<Vec<SignalId>>>,
-    pub(crate) memo_value_list: AtomicCell<Vec<Arc<dyn Value>>>,
-    pub(crate) memo_used_signals_list: AtomicCell<Vec<Vec<SignalId>>>,
-    pub(crate) render_used_signals: AtomicCell<Vec<SignalId>>,
-    pub(crate) is_first_render: AtomicBool,
+    pub(crate) hook_slots: RefCell<Vec<HookSlot>>,
+    /// What the states, memos and effects keep between renders.
+    pub(crate) slots: RefCell<SlotArena>,
+    pub(crate) render_used_signals: RefCell<Vec<SignalId>>,
+    pub(crate) listeners: RefCell<Vec<(Phase, Listener)>>,
+    /// The `Signal<T>` provided for each `T`, see `Context::provide`.
+    pub(crate) provided: RefCell<HashMap<TypeId, Rc<dyn Any>>>,
+    pub(crate) is_first_render: Cell<bool>,
+    /// Set when the parent re-rendered this component with new props, so the
+    /// next render runs even if no signal it reads changed.
+    pub(crate) is_props_changed: Cell<bool>,
 }
 
 impl Debug for ComponentInstance {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        unsafe {
-            f.debug_struct("ComponentInstance")
-                .field("component_id", &self.component_id)
-                .field("component_type_id", &self.component_type_id)
-                .field("component_type_name", &self.component_type_name)
-                .field("state_list", &self.state_list.as_ptr().as_ref().unwrap())
-                .field(
-                    "effect_used_signals_list",
-                    &self.effect_used_signals_list.as_ptr().as_ref().unwrap(),
-                )
-                .field(
-                    "memo_value_list",
-                    &self.memo_value_list.as_ptr().as_ref().unwrap(),
-                )
-                .field(
-                    "memo_used_signals_list",
-                    &self.memo_used_signals_list.as_ptr().as_ref().unwrap(),
-                )
-                .finish()
-        }
+        f.debug_struct("ComponentInstance")
+            .field("component_id", &self.component_id)
+            .field("component_type_id", &self.component_type_id)
+            .field("component_type_name", &self.component_type_name)
+            .field("slots", &self.slots)
+            .finish()
     }
 }
 
 impl ComponentInstance {
     pub(crate) fn new(
         component_id: usize,
+        parent_id: Option<usize>,
         component_type_id: TypeId,
         component_type_name: &'static str,
     ) -> Self {
         Self {
             component_id,
+            parent_id,
             component_type_id,
             component_type_name,
-            state_list: AtomicCell::new(Vec::new()),
-            effect_used_signals_list: AtomicCell::new(Vec::new()),
-            memo_value_list: AtomicCell::new(Vec::new()),
-            memo_used_signals_list: AtomicCell::new(Vec::new()),
-            render_used_signals: AtomicCell::new(Vec::new()),
-            is_first_render: AtomicBool::new(true),
+            hook_slots: RefCell::new(Vec::new()),
+            slots: RefCell::new(SlotArena::default()),
+            render_used_signals: RefCell::new(Vec::new()),
+            listeners: RefCell::new(Vec::new()),
+            provided: RefCell::new(HashMap::new()),
+            is_first_render: Cell::new(true),
+            is_props_changed: Cell::new(false),
+        }
+    }
+
+    /// Runs the pending cleanups of every effect, in the order the effects
+    /// were declared.
+    pub(crate) fn run_effect_cleanups(&self) {
+        let cleanups = self.slots.borrow_mut().take_effect_cleanups();
+        for cleanup in cleanups {
+            cleanup();
         }
     }
 }
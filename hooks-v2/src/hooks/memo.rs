@@ -1,47 +1,156 @@
 use super::*;
 
-pub(crate) fn handle_memo<'a, T: 'static + Debug + Send + Sync>(
-    ctx: &'a Context,
+pub(crate) fn handle_memo<T: 'static + Debug + Send + Sync + PartialEq>(
+    ctx: &Context,
     memo: impl FnOnce() -> T,
-) -> Signal<'a, T> {
-    unsafe {
-        let memo_value_list = ctx.instance.memo_value_list.as_ptr().as_mut().unwrap();
-        let memo_used_signals_list = ctx
-            .instance
-            .memo_used_signals_list
-            .as_ptr()
-            .as_mut()
-            .unwrap();
-        let memo_index = ctx
-            .memo_index
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-
-        let is_first_run = || memo_value_list.len() <= memo_index;
-
-        let used_signal_updated = || {
-            let used_signals = memo_used_signals_list.get(memo_index).unwrap();
-            ctx.is_used_signal_updated(used_signals)
+) -> Signal<T> {
+    if !ctx.check_hook(HookSlot::new::<T>(HookKind::Memo)) {
+        return ctx.detached_signal(memo());
+    }
+    let slot_index = next_index(&ctx.slot_index);
+    let signal_id = ctx.next_signal_id();
+
+    let is_first_run = ctx.instance.slots.borrow().is_vacant(slot_index);
+    let used_signal_updated = || {
+        let slots = ctx.instance.slots.borrow();
+        let (_, used_signals) = slots.memo(slot_index);
+        ctx.is_used_signal_updated(used_signals)
+    };
+
+    if is_first_run || ctx.is_set_state_phase() && used_signal_updated() {
+        let value = memo();
+        let subscriber = Subscriber {
+            component_id: ctx.instance.component_id,
+            hook: TrackedHook::Memo { slot_index },
         };
 
-        if is_first_run() || ctx.is_set_state_phase() && used_signal_updated() {
-            println!("memo index: {}", memo_index);
-            let value = Arc::new(memo());
-            update_or_push(memo_value_list, memo_index, value);
+        if is_first_run {
+            let mut used_signals = Vec::new();
+            ctx.runtime.take_used_signals(subscriber, &mut used_signals);
+            ctx.instance.slots.borrow_mut().claim(
+                slot_index,
+                Slot::Memo {
+                    value: Rc::new(RefCell::new(value)),
+                    used_signals,
+                },
+            );
+        } else {
+            let mut slots = ctx.instance.slots.borrow_mut();
+            ctx.runtime
+                .take_used_signals(subscriber, slots.memo_used_signals_mut(slot_index));
+            let cell = downcast_value_cell::<T>(slots.memo(slot_index).0);
+            // An equal value is not an update: what reads the memo only runs
+            // again if something else it reads changed.
+            if *cell.borrow() != value {
+                *write_value(&cell) = value;
+                ctx.mark_signal_updated(signal_id);
+            }
+        }
+    }
+
+    let value = downcast_value_cell::<T>(ctx.instance.slots.borrow().memo(slot_index).0);
+    Signal::new(value, signal_id, ctx.runtime.used_signal_ids.clone())
+}
 
-            let used_signal_ids = take_used_signals();
-            update_or_push(memo_used_signals_list, memo_index, used_signal_ids);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            println!("memo_used_signals_list: {:?}", memo_used_signals_list);
+    enum Event {
+        Add(i32),
+        Reset,
+    }
+
+    #[derive(Debug)]
+    struct Counter;
+
+    impl StaticType for Counter {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Counter>()
         }
+    }
 
-        let value = &*(Arc::into_raw(memo_value_list.get(memo_index).unwrap().clone()) as *const T);
-        let signal_id = SignalId {
-            component_id: ctx.instance.component_id,
-            signal_index: ctx
-                .signal_index
-                .fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-        };
+    impl Component for Counter {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (count, set_count) = ctx.state(|| 0);
+            let parity =
+                ctx.memo(|| if *count.get() % 2 == 0 { "even" } else { "odd" }.to_string());
+            ctx.render_with_event(
+                |event| match event {
+                    Event::Add(delta) => {
+                        let delta = *delta;
+                        set_count.mutate(move |count| *count += delta)
+                    }
+                    Event::Reset => set_count.set_if_changed(0),
+                },
+                |ctx| {
+                    log("counter");
+                    (
+                        Native::Button {
+                            label: "+1".to_string(),
+                            on_click: ctx.event(Event::Add(1)),
+                        },
+                        Native::Button {
+                            label: "+2".to_string(),
+                            on_click: ctx.event(Event::Add(2)),
+                        },
+                        Native::Button {
+                            label: "Reset".to_string(),
+                            on_click: ctx.event(Event::Reset),
+                        },
+                        Parity { parity },
+                    )
+                },
+            )
+        }
+    }
+
+    #[derive(Debug)]
+    struct Parity {
+        parity: Signal<String>,
+    }
+
+    impl StaticType for Parity {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Parity>()
+        }
+    }
+
+    impl Component for Parity {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let text = ctx.memo(|| {
+                log("label");
+                format!("The count is {}", *self.parity.get())
+            });
+            ctx.render(|| Native::Text { text })
+        }
+    }
+
+    #[test]
+    fn equal_memo_value_stops_propagation() {
+        let mut renderer = TestRenderer::new(Counter);
+        assert_eq!(take_log(), ["counter", "label"]);
+
+        renderer.click(&renderer.get_by_text("+2"));
+        assert!(take_log().is_empty());
+        renderer.get_by_text("The count is even");
+
+        renderer.click(&renderer.get_by_text("+1"));
+        assert_eq!(take_log(), ["label"]);
+        renderer.get_by_text("The count is odd");
+    }
+
+    #[test]
+    fn set_if_changed_skips_an_equal_state() {
+        let mut renderer = TestRenderer::new(Counter);
+        take_log();
+
+        renderer.click(&renderer.get_by_text("Reset"));
+        assert!(take_log().is_empty());
 
-        Signal::new(value, signal_id)
+        renderer.click(&renderer.get_by_text("+1"));
+        renderer.click(&renderer.get_by_text("Reset"));
+        assert_eq!(take_log(), ["label", "label"]);
+        renderer.get_by_text("The count is even");
     }
 }
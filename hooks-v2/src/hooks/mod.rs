@@ -1,40 +1,76 @@
+mod atom;
 mod channel;
+mod children;
 mod effect;
+mod error;
 mod event;
+mod hook_slot;
 mod instance;
 mod memo;
+mod native;
+mod native_tree;
+mod propagation;
+mod provider;
+mod reducer;
+mod registry;
 mod render;
+mod runtime;
 mod signal;
+mod slot;
 mod start;
 mod state;
+mod subscription;
+mod task;
+mod testing;
+mod timer;
 mod value;
 
-pub use channel::*;
-use crossbeam::atomic::AtomicCell;
-use effect::*;
+pub use atom::*;
+use channel::*;
+pub use children::*;
+pub use effect::*;
+pub use error::*;
 pub use event::*;
-pub use instance::*;
-pub use memo::*;
-pub use render::*;
+use hook_slot::*;
+use instance::*;
+use memo::*;
+pub use native::*;
+pub use native_tree::*;
+pub use propagation::*;
+use provider::*;
+pub use reducer::*;
+use registry::*;
+use render::*;
+pub use runtime::*;
 pub use signal::*;
+use slot::*;
 pub use start::*;
 pub use state::*;
 use std::{
     any::{Any, TypeId},
-    cell::OnceCell,
-    collections::HashSet,
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque},
     fmt::Debug,
-    sync::{atomic::AtomicUsize, Arc},
+    future::Future,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
 };
+use subscription::*;
+pub use task::*;
+#[cfg(test)]
+use test_support::*;
+pub use testing::*;
+pub use timer::*;
 pub use value::*;
 
 pub(crate) enum ContextFor {
     Mount,
     Event {
-        event_callback: EventCallback,
+        event: EventValue,
     },
     SetState {
-        updated_signals: Arc<AtomicCell<HashSet<SignalId>>>,
+        updated_signals: Rc<RefCell<HashSet<SignalId>>>,
     },
 }
 
@@ -42,38 +78,44 @@ impl Debug for ContextFor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ContextFor::Mount => write!(f, "ContextFor::Mount"),
-            ContextFor::Event { event_callback } => write!(
-                f,
-                "ContextFor::Event {{ event_callback: {:?} }}",
-                event_callback
-            ),
+            ContextFor::Event { event } => {
+                write!(f, "ContextFor::Event {{ event: {:?} }}", event)
+            }
             ContextFor::SetState { updated_signals } => write!(
                 f,
                 "ContextFor::SetState {{ updated_signals: {:?} }}",
-                unsafe { updated_signals.as_ptr().as_ref().unwrap() }
+                updated_signals.borrow()
             ),
         }
     }
 }
 
 pub struct Context {
+    runtime: Rc<RuntimeState>,
     context_for: ContextFor,
-    instance: Arc<ComponentInstance>,
-    signal_index: AtomicUsize,
-    state_index: AtomicUsize,
-    effect_index: AtomicUsize,
-    memo_index: AtomicUsize,
+    instance: Rc<ComponentInstance>,
+    hook_index: Cell<usize>,
+    signal_index: Cell<usize>,
+    slot_index: Cell<usize>,
+    listener_index: Cell<usize>,
+    is_hook_order_broken: Cell<bool>,
 }
 
 impl Context {
-    pub(crate) fn new(context_for: ContextFor, instance: Arc<ComponentInstance>) -> Self {
+    pub(crate) fn new(
+        runtime: Rc<RuntimeState>,
+        context_for: ContextFor,
+        instance: Rc<ComponentInstance>,
+    ) -> Self {
         Self {
+            runtime,
             context_for,
             instance,
-            signal_index: AtomicUsize::new(0),
-            state_index: AtomicUsize::new(0),
-            effect_index: AtomicUsize::new(0),
-            memo_index: AtomicUsize::new(0),
+            hook_index: Cell::new(0),
+            signal_index: Cell::new(0),
+            slot_index: Cell::new(0),
+            listener_index: Cell::new(0),
+            is_hook_order_broken: Cell::new(false),
         }
     }
 
@@ -84,57 +126,178 @@ impl Context {
         handle_state(self, init)
     }
 
-    pub fn effect(&self, name: &'static str, effect: impl FnOnce()) {
-        let _ = name;
-        handle_effect(self, effect);
+    /// A state updated only through actions: `reduce` applies each
+    /// dispatched action to it, see `Dispatch`.
+    pub fn reducer<State: Send + Sync + Debug + 'static, Action: Send + Sync + 'static>(
+        &self,
+        init: impl FnOnce() -> State,
+        reduce: impl Fn(&mut State, Action) + Send + Sync + 'static,
+    ) -> (Signal<State>, Dispatch<Action>) {
+        handle_reducer(self, init, reduce)
     }
 
-    pub fn render<'a, 'b, C: Component + 'b>(
+    pub fn effect<Cleanup: EffectCleanup + 'static>(
         &self,
-        render: impl 'a + FnOnce() -> C,
-    ) -> ContextDone {
+        name: &'static str,
+        effect: impl FnOnce() -> Cleanup,
+    ) {
+        handle_effect(self, name, effect);
+    }
+
+    /// Runs a future on tokio. The signals read by `task` while it makes
+    /// the future are its inputs: when one of them
+    /// changes, the running future is aborted and `task` is called again.
+    /// Unmounting aborts it too.
+    ///
+    /// The future runs on the tokio runtime `start` was called in, or on
+    /// one the runtime starts for itself.
+    pub fn spawn<Task: Future<Output = ()> + Send + 'static>(
+        &self,
+        name: &'static str,
+        task: impl FnOnce() -> Task,
+    ) {
+        handle_spawn(self, name, task);
+    }
+
+    /// Like `spawn`, with the output of the future delivered back as a
+    /// state: `Resource::Loading` until the future is done, and again
+    /// whenever an input changed and a new one is running.
+    pub fn resource<T: Send + Sync + Debug + 'static, Load: Future<Output = T> + Send + 'static>(
+        &self,
+        name: &'static str,
+        load: impl FnOnce() -> Load,
+    ) -> Signal<Resource<T>> {
+        handle_resource(self, name, load)
+    }
+
+    /// The value this runtime has for `atom`, see `Atom`. Its signal
+    /// subscribes whatever reads it like any state's does, and its
+    /// `SetState` updates every component that read it.
+    pub fn atom<T: Send + Sync + Debug + 'static>(
+        &self,
+        atom: &'static Atom<T>,
+    ) -> (Signal<T>, SetState<T>) {
+        handle_atom(self, atom)
+    }
+
+    /// Makes `value` available to every component below this one through
+    /// `use_context::<T>()`, without passing it down as props. The nearest
+    /// provider of a `T` wins.
+    ///
+    /// A consumer reads the signal itself, so when it changes only the
+    /// consumers that read it re-render. To provide a constant, wrap it in a
+    /// `memo`.
+    pub fn provide<T: 'static>(&self, value: Signal<T>) {
+        handle_provide(self, value);
+    }
+
+    /// The `Signal<T>` provided by the nearest ancestor, if any.
+    pub fn use_context<T: 'static>(&self) -> Option<Signal<T>> {
+        handle_use_context(self)
+    }
+
+    /// Sends `event` to this component's `render_with_event` handler every
+    /// `period`, until the component unmounts. A zero `period` is reported
+    /// and no interval is set.
+    ///
+    /// `period` and `event` are fixed at mount: the values passed on later
+    /// renders are ignored. To change either, render the component again
+    /// under another key.
+    pub fn interval<Event: Send + Sync + 'static>(&self, period: Duration, event: Event) {
+        handle_timer(self, Timer::Interval(period), event);
+    }
+
+    /// Sends `event` to this component's `render_with_event` handler once,
+    /// after `delay`, unless the component unmounted before. Like with
+    /// `interval`, `delay` and `event` are fixed at mount.
+    pub fn timeout<Event: Send + Sync + 'static>(&self, delay: Duration, event: Event) {
+        handle_timer(self, Timer::Timeout(delay), event);
+    }
+
+    /// Listens to the native events of every node below this component,
+    /// see `NativeEvent`.
+    pub fn listen(&self, phase: Phase, listener: impl Fn(&NativeEvent) + 'static) {
+        handle_listen(self, phase, listener);
+    }
+
+    pub fn render<R: IntoChildren>(&self, render: impl FnOnce() -> R) -> ContextDone {
+        if self.is_render_skipped() {
+            return ContextDone::NoRender;
+        }
         match &self.context_for {
             ContextFor::Mount | ContextFor::SetState { .. } => {
-                let child = handle_render(self, render);
-                match child {
-                    Some(child) => ContextDone::Rendered { child },
+                let children = handle_render(self, render);
+                match children {
+                    Some(children) => ContextDone::Rendered { children },
                     None => ContextDone::NoRender,
                 }
             }
             ContextFor::Event { .. } => {
-                unreachable!()
+                self.runtime.report_error(RuactError::EventNotHandled {
+                    component_type_name: self.instance.component_type_name,
+                });
+                ContextDone::NoRender
             }
         }
     }
 
-    pub fn render_with_event<'me, 'a, 'b, C: Component + 'b, Event: 'static + Send + Sync>(
-        &'me self,
-        on_event: impl 'a + FnOnce(&Event),
-        render: impl 'a + FnOnce(EventContext<Event>) -> C,
+    pub fn render_with_event<R: IntoChildren, Event: 'static + Send + Sync>(
+        &self,
+        on_event: impl FnOnce(&Event),
+        render: impl FnOnce(EventContext<Event>) -> R,
     ) -> ContextDone {
+        if self.is_render_skipped() {
+            return ContextDone::NoRender;
+        }
         match &self.context_for {
             ContextFor::Mount | ContextFor::SetState { .. } => {
-                let child = handle_render_with_event(self, render);
-                match child {
-                    Some(child) => ContextDone::Rendered { child },
+                let children = handle_render_with_event(self, render);
+                match children {
+                    Some(children) => ContextDone::Rendered { children },
                     None => ContextDone::NoRender,
                 }
             }
-            ContextFor::Event { event_callback } => {
-                assert_eq!(event_callback.component_id, self.instance.component_id);
-                on_event(event_callback.event.downcast_ref().unwrap());
+            ContextFor::Event { event } => {
+                match event.downcast_ref() {
+                    Some(event) => on_event(event),
+                    None => self.runtime.report_error(RuactError::EventTypeMismatch {
+                        component_type_name: self.instance.component_type_name,
+                        expected_type_name: std::any::type_name::<Event>(),
+                    }),
+                }
                 ContextDone::NoRender
             }
         }
     }
 
-    pub fn memo<T: 'static + Debug + Send + Sync>(
+    /// Derives a value from the signals `memo` reads, computing it again
+    /// when one of them changes. If the new value equals the old one, what
+    /// reads the memo is left as it is.
+    pub fn memo<T: 'static + Debug + Send + Sync + PartialEq>(
         &self,
         memo: impl FnOnce() -> T,
-    ) -> Signal<'_, T> {
+    ) -> Signal<T> {
         handle_memo(self, memo)
     }
 
+    /// A component whose hooks were out of order neither renders nor
+    /// handles its event this pass, see `check_hook`. What it read is
+    /// dropped with it.
+    fn is_render_skipped(&self) -> bool {
+        if self.check_hook_count() {
+            return false;
+        }
+        self.runtime.discard_used_signals();
+        true
+    }
+
+    fn next_signal_id(&self) -> SignalId {
+        SignalId {
+            component_id: self.instance.component_id,
+            signal_index: next_index(&self.signal_index),
+        }
+    }
+
     fn is_set_state_phase(&self) -> bool {
         match &self.context_for {
             ContextFor::Mount | ContextFor::Event { .. } => false,
@@ -142,6 +305,17 @@ impl Context {
         }
     }
 
+    /// Lets hooks later in this pass, in this component or below, see that a
+    /// signal it owns (a memo) changed while propagating.
+    fn mark_signal_updated(&self, signal_id: SignalId) {
+        match &self.context_for {
+            ContextFor::Mount | ContextFor::Event { .. } => unreachable!(),
+            ContextFor::SetState { updated_signals } => {
+                updated_signals.borrow_mut().insert(signal_id);
+            }
+        }
+    }
+
     fn is_used_signal_updated<'a>(
         &self,
         signal_ids: impl IntoIterator<Item = &'a SignalId>,
@@ -149,65 +323,58 @@ impl Context {
         match &self.context_for {
             ContextFor::Mount | ContextFor::Event { .. } => unreachable!(),
             ContextFor::SetState { updated_signals } => {
-                signal_ids.into_iter().any(|signal_id| unsafe {
-                    updated_signals
-                        .as_ptr()
-                        .as_ref()
-                        .unwrap()
-                        .contains(&signal_id)
-                })
+                let updated_signals = updated_signals.borrow();
+                signal_ids
+                    .into_iter()
+                    .any(|signal_id| updated_signals.contains(signal_id))
             }
         }
     }
 }
 
 pub struct EventContext<Event: 'static> {
+    tx: channel::Sender,
     component_id: usize,
     _event: std::marker::PhantomData<Event>,
 }
 
 impl<Event: 'static + Send + Sync> EventContext<Event> {
-    fn new(component_id: usize) -> Self {
+    fn new(tx: channel::Sender, component_id: usize) -> Self {
         Self {
+            tx,
             component_id,
             _event: std::marker::PhantomData,
         }
     }
     pub fn event(&self, event: Event) -> EventCallback {
         EventCallback {
+            tx: self.tx.clone(),
+            component_id: self.component_id,
+            source: EventSource::Event(Arc::new(event)),
+        }
+    }
+
+    /// An event made from data the backend passes when it fires, like the
+    /// new text of an input. `Payload` must be the type the backend passes
+    /// for that prop, see `Native`.
+    pub fn event_with<Payload: Any + Send>(
+        &self,
+        event: impl Fn(Payload) -> Event + Send + Sync + 'static,
+    ) -> EventCallback {
+        EventCallback {
+            tx: self.tx.clone(),
             component_id: self.component_id,
-            event: Arc::new(event),
+            source: EventSource::map(event),
         }
     }
 }
 
 #[derive(Debug)]
 pub enum ContextDone {
-    Rendered { child: OnceCell<Box<dyn Component>> },
+    Rendered { children: Vec<Child> },
     NoRender,
 }
 
-#[derive(Debug)]
-pub enum Native {
-    Button { on_click: EventCallback },
-}
-
-impl StaticType for Native {
-    fn static_type_id(&self) -> TypeId {
-        TypeId::of::<Native>()
-    }
-}
-
-impl Component for Native {
-    fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
-        ContextDone::NoRender
-    }
-
-    fn native(&self) -> &Native {
-        self
-    }
-}
-
 pub trait Component: StaticType + Debug {
     fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone;
     fn native(&self) -> &Native {
@@ -223,6 +390,12 @@ pub trait StaticType {
     }
 }
 
+fn next_index(index: &Cell<usize>) -> usize {
+    let next = index.get();
+    index.set(next + 1);
+    next
+}
+
 fn update_or_push<T>(vector: &mut Vec<T>, index: usize, value: T) {
     if let Some(prev) = vector.get_mut(index) {
         *prev = value;
@@ -231,3 +404,121 @@ fn update_or_push<T>(vector: &mut Vec<T>, index: usize, value: T) {
         vector.insert(index, value);
     }
 }
+
+/// Fixtures shared by the test modules.
+#[cfg(test)]
+mod test_support {
+    use super::*;
+
+    thread_local! {
+        static LOG: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+        static REPORTED: RefCell<Vec<RuactError>> = const { RefCell::new(vec![]) };
+    }
+
+    /// Appends to this thread's log. Every test runs on its own thread, so
+    /// it only sees what it logged itself.
+    pub(crate) fn log(entry: impl Into<String>) {
+        LOG.with(|log| log.borrow_mut().push(entry.into()));
+    }
+
+    pub(crate) fn take_log() -> Vec<String> {
+        LOG.with(|log| log.take())
+    }
+
+    /// An error reporter that keeps what it gets for `take_reported`.
+    pub(crate) fn collect_error(error: &RuactError) {
+        REPORTED.with(|reported| reported.borrow_mut().push(error.clone()));
+    }
+
+    pub(crate) fn take_reported() -> Vec<RuactError> {
+        REPORTED.with(|reported| reported.take())
+    }
+
+    /// Keeps a number that its `i32` events add to, and renders nothing.
+    #[derive(Debug)]
+    pub(crate) struct Adder;
+
+    impl StaticType for Adder {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Adder>()
+        }
+    }
+
+    impl Component for Adder {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (sum, set_sum) = ctx.state(|| 0);
+            ctx.render_with_event(
+                |&delta: &i32| set_sum.mutate(move |sum| *sum += delta),
+                |_| {
+                    let _ = *sum.get();
+                },
+            )
+        }
+    }
+
+    /// Mounts `component` on a runtime without an event loop, so a test
+    /// feeds it batches with `run_batch` itself. What the runtime reports
+    /// is kept for `take_reported`.
+    pub(crate) fn mount(
+        component: impl Component + 'static,
+    ) -> (Rc<RuntimeState>, Rc<ComponentHolder>) {
+        let runtime = RuntimeState::detached();
+        *runtime.error_reporter.borrow_mut() = Rc::new(collect_error);
+        let root_holder = mount_root(&runtime, Box::new(component));
+        (runtime, root_holder)
+    }
+
+    /// The item an `EventContext::event` callback sends when fired.
+    pub(crate) fn event_item(
+        runtime: &RuntimeState,
+        component_id: usize,
+        event: impl Any + Send + Sync,
+    ) -> Item {
+        Item::Event {
+            event_callback: EventCallback {
+                tx: runtime.tx.clone(),
+                component_id,
+                source: EventSource::Event(Arc::new(event)),
+            },
+            payload: Box::new(()),
+        }
+    }
+
+    pub(crate) fn state_id(holder: &ComponentHolder, state_index: usize) -> SignalId {
+        SignalId {
+            component_id: holder.component_instance.component_id,
+            signal_index: state_index,
+        }
+    }
+
+    /// The item `SetState::set` sends for the `state_index`th state of
+    /// `holder`.
+    pub(crate) fn set_state(
+        holder: &ComponentHolder,
+        state_index: usize,
+        value: impl Value + 'static,
+    ) -> Item {
+        Item::SetStateItem(SetStateItem::Set {
+            signal_id: state_id(holder, state_index),
+            state_index,
+            value: Box::new(value),
+        })
+    }
+
+    /// The item `SetState::mutate` sends for the `state_index`th state of
+    /// `holder`.
+    pub(crate) fn mutate_state<T: Any>(
+        holder: &ComponentHolder,
+        state_index: usize,
+        mutate: impl FnOnce(&mut T) + Send + Sync + 'static,
+    ) -> Item {
+        Item::SetStateItem(SetStateItem::Mutate {
+            signal_id: state_id(holder, state_index),
+            state_index,
+            mutate: Box::new(move |state| {
+                mutate(state.as_any_mut().downcast_mut().unwrap());
+                Ok(true)
+            }),
+        })
+    }
+}
@@ -0,0 +1,197 @@
+use super::*;
+use std::cell::RefCell;
+
+/// The elements a backend knows how to show. Every other component
+/// eventually renders down to these.
+///
+/// Props that hold a `Signal` stay bound to it: the node is updated when
+/// the signal changes, without re-rendering the component that rendered it.
+#[derive(Debug)]
+pub enum Native {
+    /// A container laying out its children.
+    View { children: NativeChildren },
+    /// A piece of text.
+    Text { text: Signal<String> },
+    /// `on_click` is fired without a payload.
+    Button {
+        label: String,
+        on_click: EventCallback,
+    },
+    /// A single line of editable text. `on_change` is fired with an
+    /// `InputChanged` on every edit; `value` is what is shown, so the input
+    /// only changes when the state behind it does.
+    TextInput {
+        value: Signal<String>,
+        on_change: EventCallback,
+    },
+    /// A toggle. Like `TextInput`, it shows `checked` and only asks for a
+    /// change, by firing `on_toggle` with a `Toggled`.
+    Checkbox {
+        checked: Signal<bool>,
+        on_toggle: EventCallback,
+    },
+    /// An image loaded from a path.
+    Image { src: String },
+}
+
+impl Native {
+    pub fn view(children: impl IntoChildren) -> Self {
+        Native::View {
+            children: NativeChildren::new(children),
+        }
+    }
+}
+
+/// The payload of `TextInput`'s `on_change`: the text after the edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputChanged {
+    pub value: String,
+}
+
+/// The payload of `Checkbox`'s `on_toggle`: the state the user asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Toggled {
+    pub checked: bool,
+}
+
+/// The children of a `View`. They are handed to the runtime on the render
+/// after the `View` was created, like the children any component renders.
+pub struct NativeChildren(RefCell<Vec<Child>>);
+
+impl NativeChildren {
+    pub fn new(children: impl IntoChildren) -> Self {
+        Self(RefCell::new(children.into_children()))
+    }
+}
+
+impl Debug for NativeChildren {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.try_borrow() {
+            Ok(children) => Debug::fmt(&*children, f),
+            Err(_) => write!(f, "<being rendered>"),
+        }
+    }
+}
+
+/// Which element a native node is, without its props.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NativeKind {
+    View,
+    Text,
+    Button,
+    TextInput,
+    Checkbox,
+    Image,
+}
+
+/// One prop of a native node, as sent to the backend.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prop {
+    Text(String),
+    Label(String),
+    Value(String),
+    Checked(bool),
+    Src(String),
+    OnClick(EventCallback),
+    OnChange(EventCallback),
+    OnToggle(EventCallback),
+    TestId(String),
+}
+
+impl Prop {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Prop::Text(_) => "text",
+            Prop::Label(_) => "label",
+            Prop::Value(_) => "value",
+            Prop::Checked(_) => "checked",
+            Prop::Src(_) => "src",
+            Prop::OnClick(_) => "on_click",
+            Prop::OnChange(_) => "on_change",
+            Prop::OnToggle(_) => "on_toggle",
+            Prop::TestId(_) => "test_id",
+        }
+    }
+
+    pub(crate) fn into_event_callback(self) -> Option<EventCallback> {
+        match self {
+            Prop::OnClick(callback) | Prop::OnChange(callback) | Prop::OnToggle(callback) => {
+                Some(callback)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether both are values of the same prop.
+    pub(crate) fn is_same_prop(&self, other: &Prop) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl Native {
+    pub fn kind(&self) -> NativeKind {
+        match self {
+            Native::View { .. } => NativeKind::View,
+            Native::Text { .. } => NativeKind::Text,
+            Native::Button { .. } => NativeKind::Button,
+            Native::TextInput { .. } => NativeKind::TextInput,
+            Native::Checkbox { .. } => NativeKind::Checkbox,
+            Native::Image { .. } => NativeKind::Image,
+        }
+    }
+
+    /// The current props. Reading the bound signals here doesn't subscribe
+    /// anything to them.
+    pub fn props(&self) -> Vec<Prop> {
+        fn peek<T: Clone>(signal: &Signal<T>) -> T {
+            (*signal.peek()).clone()
+        }
+        match self {
+            Native::View { .. } => vec![],
+            Native::Text { text } => vec![Prop::Text(peek(text))],
+            Native::Button { label, on_click } => {
+                vec![Prop::Label(label.clone()), Prop::OnClick(on_click.clone())]
+            }
+            Native::TextInput { value, on_change } => {
+                vec![Prop::Value(peek(value)), Prop::OnChange(on_change.clone())]
+            }
+            Native::Checkbox { checked, on_toggle } => vec![
+                Prop::Checked(peek(checked)),
+                Prop::OnToggle(on_toggle.clone()),
+            ],
+            Native::Image { src } => vec![Prop::Src(src.clone())],
+        }
+    }
+
+    /// Subscribes the node to the signals its props are bound to.
+    fn track_signals(&self) {
+        match self {
+            Native::Text { text } => text.track(),
+            Native::TextInput { value, .. } => value.track(),
+            Native::Checkbox { checked, .. } => checked.track(),
+            Native::View { .. } | Native::Button { .. } | Native::Image { .. } => {}
+        }
+    }
+}
+
+impl StaticType for Native {
+    fn static_type_id(&self) -> TypeId {
+        TypeId::of::<Native>()
+    }
+}
+
+impl Component for Native {
+    fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+        ctx.render(|| {
+            self.track_signals();
+            match self {
+                Native::View { children } => children.0.take(),
+                _ => vec![],
+            }
+        })
+    }
+
+    fn native(&self) -> &Native {
+        self
+    }
+}
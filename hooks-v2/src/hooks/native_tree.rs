@@ -0,0 +1,337 @@
+use super::*;
+
+/// Shows the native nodes the runtime renders. The runtime drives it with
+/// the patches that turn what it showed last into what was just rendered,
+/// and ends every such group with `Patch::Commit`.
+pub trait NativeBackend {
+    /// Called once, before the first patch, with the handle to fire the
+    /// user's interactions through.
+    fn connect(&mut self, _handle: RuntimeHandle) {}
+
+    fn apply(&mut self, patch: Patch);
+}
+
+/// One step of updating the native tree. Nodes are identified by the id of
+/// the `Native` component they show, and a `parent_id` of `None` is the
+/// root container.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Patch {
+    /// Creates a detached node. Its props follow as `SetProp`s.
+    CreateNode { node_id: usize, kind: NativeKind },
+    /// Sets a prop that is new or changed. Event callbacks are created on
+    /// every render, so they are sent again whenever their component
+    /// re-renders.
+    SetProp { node_id: usize, prop: Prop },
+    /// Moves a node to `index` among the children of `parent_id`, detaching
+    /// it from where it was first if it is attached.
+    InsertChild {
+        parent_id: Option<usize>,
+        node_id: usize,
+        index: usize,
+    },
+    /// Removes a node. Sent for every node of a removed subtree, children
+    /// first.
+    RemoveNode { node_id: usize },
+    /// Everything since the previous commit has been sent.
+    Commit,
+}
+
+/// The native tree as the backend has it, so reconciling can be turned
+/// into patches. Mounting, unmounting and re-rendering a `Native` queue
+/// patches right away; child order is only settled in `set_children`, for
+/// the containers marked dirty since the last flush.
+#[derive(Default, Debug)]
+pub(crate) struct NativeTree {
+    props: HashMap<usize, Vec<Prop>>,
+    children: HashMap<Option<usize>, Vec<usize>>,
+    dirty: Vec<Option<usize>>,
+    patches: Vec<Patch>,
+}
+
+impl NativeTree {
+    pub(crate) fn create(&mut self, node_id: usize, kind: NativeKind, props: Vec<Prop>) {
+        self.patches.push(Patch::CreateNode { node_id, kind });
+        for prop in &props {
+            self.patches.push(Patch::SetProp {
+                node_id,
+                prop: prop.clone(),
+            });
+        }
+        self.props.insert(node_id, props);
+        self.mark_dirty(Some(node_id));
+    }
+
+    pub(crate) fn update(&mut self, node_id: usize, props: Vec<Prop>) {
+        let prev_props = self
+            .props
+            .insert(node_id, props.clone())
+            .unwrap_or_default();
+        for prop in props {
+            let changed = prev_props
+                .iter()
+                .find(|prev| prev.is_same_prop(&prop))
+                .is_none_or(|prev| *prev != prop);
+            if changed {
+                self.patches.push(Patch::SetProp { node_id, prop });
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, node_id: usize) {
+        self.props.remove(&node_id);
+        self.children.remove(&Some(node_id));
+        self.patches.push(Patch::RemoveNode { node_id });
+    }
+
+    /// Marks a container whose native children may have changed.
+    pub(crate) fn mark_dirty(&mut self, container: Option<usize>) {
+        if !self.dirty.contains(&container) {
+            self.dirty.push(container);
+        }
+    }
+
+    /// The dirty containers that still exist, in the order they were marked.
+    pub(crate) fn take_dirty(&mut self) -> Vec<Option<usize>> {
+        let mut dirty = std::mem::take(&mut self.dirty);
+        dirty.retain(|container| container.is_none_or(|node_id| self.props.contains_key(&node_id)));
+        dirty
+    }
+
+    /// Queues the inserts that reorder a container's children, as the
+    /// backend has them, into `next_children`.
+    pub(crate) fn set_children(&mut self, container: Option<usize>, next_children: Vec<usize>) {
+        let mut children = self.children.remove(&container).unwrap_or_default();
+        children.retain(|node_id| self.props.contains_key(node_id));
+        for (index, node_id) in next_children.iter().enumerate() {
+            if children.get(index) == Some(node_id) {
+                continue;
+            }
+            children.retain(|child| child != node_id);
+            children.insert(index, *node_id);
+            self.patches.push(Patch::InsertChild {
+                parent_id: container,
+                node_id: *node_id,
+                index,
+            });
+        }
+        self.children.insert(container, next_children);
+    }
+
+    /// The patches queued since the last call, followed by a commit.
+    pub(crate) fn take_patches(&mut self) -> Vec<Patch> {
+        let mut patches = std::mem::take(&mut self.patches);
+        if !patches.is_empty() {
+            patches.push(Patch::Commit);
+        }
+        patches
+    }
+}
+
+/// The props sent for a native node.
+pub(crate) fn native_props(native: &Native, test_id: Option<&str>) -> Vec<Prop> {
+    let mut props = native.props();
+    props.extend(test_id.map(|test_id| Prop::TestId(test_id.to_string())));
+    props
+}
+
+/// The native node that the native children of `component_id` are shown
+/// in: the component itself if it is a `Native`, otherwise its nearest
+/// `Native` ancestor, or the root container if there is none.
+pub(crate) fn native_container(runtime: &RuntimeState, component_id: usize) -> Option<usize> {
+    let registry = runtime.registry.borrow();
+    let container = std::iter::once(component_id)
+        .chain(registry.ancestors(component_id))
+        .find(|component_id| {
+            registry
+                .get(*component_id)
+                .is_ok_and(|holder| holder.is_native())
+        });
+    container
+}
+
+/// The nearest `Native` descendants of a holder, in render order.
+pub(crate) fn native_children(holder: &ComponentHolder) -> Vec<usize> {
+    let mut native_children = vec![];
+    let mut stack = holder
+        .children
+        .borrow()
+        .iter()
+        .rev()
+        .cloned()
+        .collect::<Vec<_>>();
+    while let Some(child) = stack.pop() {
+        if child.is_native() {
+            native_children.push(child.component_instance.component_id);
+        } else {
+            stack.extend(child.children.borrow().iter().rev().cloned());
+        }
+    }
+    native_children
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingBackend {
+        patches: Vec<String>,
+    }
+
+    impl NativeBackend for RecordingBackend {
+        fn apply(&mut self, patch: Patch) {
+            self.patches.push(match patch {
+                Patch::CreateNode { node_id, kind } => format!("create {node_id} {kind:?}"),
+                Patch::SetProp { node_id, prop } => format!("set {node_id} {}", prop.name()),
+                Patch::InsertChild {
+                    parent_id,
+                    node_id,
+                    index,
+                } => format!("insert {parent_id:?} {node_id} {index}"),
+                Patch::RemoveNode { node_id } => format!("remove {node_id}"),
+                Patch::Commit => "commit".to_string(),
+            });
+        }
+    }
+
+    #[derive(Debug)]
+    struct Buttons;
+
+    impl StaticType for Buttons {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Buttons>()
+        }
+    }
+
+    impl Component for Buttons {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (keys, _) = ctx.state(|| vec![1, 2, 3]);
+            ctx.render_with_event(
+                |_: &i32| {},
+                |ctx| {
+                    keys.get()
+                        .iter()
+                        .map(|&key| {
+                            keyed(
+                                key,
+                                Native::Button {
+                                    label: key.to_string(),
+                                    on_click: ctx.event(key),
+                                },
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn reconciling_is_sent_to_the_backend_as_patches() {
+        let mut backend = RecordingBackend::default();
+        let (runtime, root_holder) = mount(Buttons);
+        let [a, b, c] = native_children(&root_holder)[..] else {
+            unreachable!()
+        };
+        flush_native_tree(&runtime, &root_holder, &mut backend);
+        assert_eq!(
+            std::mem::take(&mut backend.patches),
+            [
+                format!("create {a} Button"),
+                format!("set {a} label"),
+                format!("set {a} on_click"),
+                format!("create {b} Button"),
+                format!("set {b} label"),
+                format!("set {b} on_click"),
+                format!("create {c} Button"),
+                format!("set {c} label"),
+                format!("set {c} on_click"),
+                format!("insert None {a} 0"),
+                format!("insert None {b} 1"),
+                format!("insert None {c} 2"),
+                "commit".to_string(),
+            ]
+        );
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, vec![3, 1])],
+        );
+        flush_native_tree(&runtime, &root_holder, &mut backend);
+        assert_eq!(
+            backend.patches,
+            [
+                format!("remove {b}"),
+                format!("set {c} on_click"),
+                format!("set {a} on_click"),
+                format!("insert None {c} 0"),
+                "commit".to_string(),
+            ]
+        );
+    }
+
+    #[derive(Debug)]
+    struct Label;
+
+    impl StaticType for Label {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Label>()
+        }
+    }
+
+    impl Component for Label {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (text, _) = ctx.state(|| "a".to_string());
+            ctx.render(|| {
+                log("label");
+                Native::view((
+                    Native::Text { text },
+                    Native::Image {
+                        src: "icon.png".to_string(),
+                    },
+                ))
+            })
+        }
+    }
+
+    #[test]
+    fn bound_signal_updates_only_its_node() {
+        let mut backend = RecordingBackend::default();
+        let (runtime, root_holder) = mount(Label);
+        let [view] = native_children(&root_holder)[..] else {
+            unreachable!()
+        };
+        let [text, image] = native_children(&runtime.find_component_by_id(view).unwrap())[..]
+        else {
+            unreachable!()
+        };
+        flush_native_tree(&runtime, &root_holder, &mut backend);
+        assert_eq!(
+            std::mem::take(&mut backend.patches),
+            [
+                format!("create {view} View"),
+                format!("create {text} Text"),
+                format!("set {text} text"),
+                format!("create {image} Image"),
+                format!("set {image} src"),
+                format!("insert Some({view}) {text} 0"),
+                format!("insert Some({view}) {image} 1"),
+                format!("insert None {view} 0"),
+                "commit".to_string(),
+            ]
+        );
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, "b".to_string())],
+        );
+        flush_native_tree(&runtime, &root_holder, &mut backend);
+        assert_eq!(
+            backend.patches,
+            [format!("set {text} text"), "commit".to_string()]
+        );
+        assert_eq!(take_log(), ["label"]);
+    }
+}
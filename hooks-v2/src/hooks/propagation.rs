@@ -0,0 +1,255 @@
+use super::*;
+
+pub(crate) type Listener = Rc<dyn Fn(&NativeEvent)>;
+
+/// When a listener sees an event: on the way down from the root to the
+/// target, or on the way back up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Capture,
+    Bubble,
+}
+
+/// An interaction with a native node, on its way through the components
+/// above the node.
+///
+/// It first passes every ancestor's `Phase::Capture` listeners from the
+/// root down, then their `Phase::Bubble` listeners from the node up. Once
+/// it is through, the default action runs: the node's own callback prop of
+/// the same name, fired with the payload.
+pub struct NativeEvent {
+    name: &'static str,
+    target: usize,
+    payload: Box<dyn Any + Send>,
+    phase: Cell<Phase>,
+    is_propagation_stopped: Cell<bool>,
+    is_default_prevented: Cell<bool>,
+}
+
+impl NativeEvent {
+    /// The name of the callback prop the event is for, like `"on_click"`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The node the event happened on.
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    pub fn payload<Payload: Any>(&self) -> Option<&Payload> {
+        self.payload.downcast_ref()
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase.get()
+    }
+
+    /// Keeps the event from reaching the listeners after this one. The
+    /// default action still runs.
+    pub fn stop_propagation(&self) {
+        self.is_propagation_stopped.set(true);
+    }
+
+    /// Keeps the node's own callback from being fired.
+    pub fn prevent_default(&self) {
+        self.is_default_prevented.set(true);
+    }
+
+    pub fn is_default_prevented(&self) -> bool {
+        self.is_default_prevented.get()
+    }
+}
+
+impl Debug for NativeEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeEvent")
+            .field("name", &self.name)
+            .field("target", &self.target)
+            .field("phase", &self.phase.get())
+            .finish()
+    }
+}
+
+pub(crate) fn handle_listen(
+    ctx: &Context,
+    phase: Phase,
+    listener: impl Fn(&NativeEvent) + 'static,
+) {
+    if !ctx.check_hook(HookSlot::new::<()>(HookKind::Listener { phase })) {
+        return;
+    }
+    let listener_index = next_index(&ctx.listener_index);
+    if let ContextFor::Event { .. } = ctx.context_for {
+        return;
+    }
+    update_or_push(
+        &mut ctx.instance.listeners.borrow_mut(),
+        listener_index,
+        (phase, Rc::new(listener) as Listener),
+    );
+}
+
+pub(crate) fn dispatch_native_event(
+    runtime: &Rc<RuntimeState>,
+    node_id: usize,
+    name: &'static str,
+    payload: Box<dyn Any + Send>,
+) -> Result<(), RuactError> {
+    let target = runtime.find_component_by_id(node_id)?;
+    let ancestors = {
+        let registry = runtime.registry.borrow();
+        registry
+            .ancestors(node_id)
+            .map(|component_id| registry.get(component_id))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let event = NativeEvent {
+        name,
+        target: node_id,
+        payload,
+        phase: Cell::new(Phase::Capture),
+        is_propagation_stopped: Cell::new(false),
+        is_default_prevented: Cell::new(false),
+    };
+    let path = ancestors
+        .iter()
+        .rev()
+        .map(|holder| (Phase::Capture, holder))
+        .chain(ancestors.iter().map(|holder| (Phase::Bubble, holder)));
+    for (phase, holder) in path {
+        if event.is_propagation_stopped.get() {
+            break;
+        }
+        event.phase.set(phase);
+        let listeners = holder.component_instance.listeners.borrow().clone();
+        for (_, listener) in listeners
+            .iter()
+            .filter(|(listen_phase, _)| *listen_phase == phase)
+        {
+            listener(&event);
+        }
+        runtime.discard_used_signals();
+    }
+
+    if event.is_default_prevented.get() {
+        return Ok(());
+    }
+    let callback = target
+        .component
+        .borrow()
+        .native()
+        .props()
+        .into_iter()
+        .find(|prop| prop.name() == name)
+        .and_then(|prop| prop.into_event_callback());
+    match callback {
+        Some(callback) => dispatch_event(runtime, callback, event.payload),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Toolbar {
+        stop_in_capture: bool,
+        prevent_default: bool,
+    }
+
+    impl StaticType for Toolbar {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Toolbar>()
+        }
+    }
+
+    impl Component for Toolbar {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let stop_in_capture = self.stop_in_capture;
+            ctx.listen(Phase::Capture, move |event| {
+                log("toolbar capture");
+                if stop_in_capture {
+                    event.stop_propagation();
+                }
+            });
+            ctx.listen(Phase::Bubble, |_| log("toolbar bubble"));
+            let prevent_default = self.prevent_default;
+            ctx.render(move || SaveButton { prevent_default })
+        }
+    }
+
+    #[derive(Debug)]
+    struct SaveButton {
+        prevent_default: bool,
+    }
+
+    impl StaticType for SaveButton {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<SaveButton>()
+        }
+    }
+
+    impl Component for SaveButton {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            ctx.listen(Phase::Capture, |_| log("button capture"));
+            let prevent_default = self.prevent_default;
+            ctx.listen(Phase::Bubble, move |event| {
+                log("button bubble");
+                if prevent_default {
+                    event.prevent_default();
+                }
+            });
+            ctx.render_with_event(
+                |_: &()| log("saved"),
+                |ctx| Native::Button {
+                    label: "Save".to_string(),
+                    on_click: ctx.event(()),
+                },
+            )
+        }
+    }
+
+    fn click_save(stop_in_capture: bool, prevent_default: bool) -> Vec<String> {
+        let mut renderer = TestRenderer::new(Toolbar {
+            stop_in_capture,
+            prevent_default,
+        });
+        renderer.click(&renderer.get_by_text("Save"));
+        take_log()
+    }
+
+    #[test]
+    fn captures_down_then_bubbles_up_before_the_callback() {
+        assert_eq!(
+            click_save(false, false),
+            [
+                "toolbar capture",
+                "button capture",
+                "button bubble",
+                "toolbar bubble",
+                "saved",
+            ]
+        );
+    }
+
+    #[test]
+    fn stopped_propagation_skips_the_other_listeners_only() {
+        assert_eq!(click_save(true, false), ["toolbar capture", "saved"]);
+    }
+
+    #[test]
+    fn prevented_default_keeps_the_callback_from_firing() {
+        assert_eq!(
+            click_save(false, true),
+            [
+                "toolbar capture",
+                "button capture",
+                "button bubble",
+                "toolbar bubble",
+            ]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use super::*;
+
+pub(crate) fn handle_provide<T: 'static>(ctx: &Context, value: Signal<T>) {
+    if !ctx.check_hook(HookSlot::new::<T>(HookKind::Provide)) {
+        return;
+    }
+    ctx.instance
+        .provided
+        .borrow_mut()
+        .insert(TypeId::of::<T>(), Rc::new(value));
+}
+
+/// Looks the nearest provider of a `T` up, starting at the parent. Nothing
+/// is subscribed here; the consumer is once it reads the signal.
+pub(crate) fn handle_use_context<T: 'static>(ctx: &Context) -> Option<Signal<T>> {
+    if !ctx.check_hook(HookSlot::new::<T>(HookKind::UseContext)) {
+        return None;
+    }
+    let parent_id = ctx.instance.parent_id?;
+    let registry = ctx.runtime.registry.borrow();
+    let value = std::iter::once(parent_id)
+        .chain(registry.ancestors(parent_id))
+        .find_map(|component_id| {
+            let holder = registry.get(component_id).ok()?;
+            let provided = holder.component_instance.provided.borrow();
+            let value = provided.get(&TypeId::of::<T>())?;
+            value.downcast_ref::<Signal<T>>().cloned()
+        });
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Theme {
+        Light,
+        Dark,
+        HighContrast,
+    }
+
+    #[derive(Debug)]
+    struct App;
+
+    impl StaticType for App {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<App>()
+        }
+    }
+
+    impl Component for App {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (theme, set_theme) = ctx.state(|| Theme::Light);
+            ctx.provide(theme);
+            ctx.render_with_event(
+                |_: &()| set_theme.mutate(|theme| *theme = Theme::Dark),
+                |ctx| {
+                    log("app");
+                    (
+                        Native::Button {
+                            label: "Go dark".to_string(),
+                            on_click: ctx.event(()),
+                        },
+                        Toolbar,
+                        Panel,
+                    )
+                },
+            )
+        }
+    }
+
+    #[derive(Debug)]
+    struct Toolbar;
+
+    impl StaticType for Toolbar {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Toolbar>()
+        }
+    }
+
+    impl Component for Toolbar {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            ctx.render(|| {
+                log("toolbar");
+                ThemedLabel
+            })
+        }
+    }
+
+    /// Provides a `Theme` of its own under the one `App` provides.
+    #[derive(Debug)]
+    struct Panel;
+
+    impl StaticType for Panel {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Panel>()
+        }
+    }
+
+    impl Component for Panel {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (theme, _) = ctx.state(|| Theme::HighContrast);
+            ctx.provide(theme);
+            ctx.render(|| {
+                log("panel");
+                ThemedLabel
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct ThemedLabel;
+
+    impl StaticType for ThemedLabel {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<ThemedLabel>()
+        }
+    }
+
+    impl Component for ThemedLabel {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let theme = ctx.use_context::<Theme>().expect("no theme provided");
+            let missing = ctx.use_context::<String>();
+            ctx.render(|| {
+                log("label");
+                assert!(missing.is_none());
+                Native::Image {
+                    src: format!("{:?}.png", *theme.get()),
+                }
+            })
+        }
+    }
+
+    fn image_srcs(renderer: &TestRenderer) -> Vec<Option<Prop>> {
+        renderer
+            .find_all_by_kind(NativeKind::Image)
+            .into_iter()
+            .map(|image| image.prop("src").cloned())
+            .collect()
+    }
+
+    fn src(src: &str) -> Option<Prop> {
+        Some(Prop::Src(src.to_string()))
+    }
+
+    #[test]
+    fn consumers_read_the_nearest_provider_and_rerender_alone() {
+        let mut renderer = TestRenderer::new(App);
+        assert_eq!(take_log(), ["app", "toolbar", "label", "panel", "label"]);
+        assert_eq!(
+            image_srcs(&renderer),
+            [src("Light.png"), src("HighContrast.png")]
+        );
+
+        renderer.click(&renderer.get_by_text("Go dark"));
+
+        // The label under `Panel` reads the inner theme, so the outer one
+        // changing leaves it alone.
+        assert_eq!(take_log(), ["label"]);
+        assert_eq!(
+            image_srcs(&renderer),
+            [src("Dark.png"), src("HighContrast.png")]
+        );
+    }
+}
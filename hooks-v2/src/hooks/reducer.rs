@@ -0,0 +1,134 @@
+use super::*;
+
+/// Queues actions for a `reducer`. Like `SetState`, nothing changes
+/// immediately: every action queued before the runtime wakes up is reduced
+/// in order, and then the tree re-renders once for the whole batch.
+pub struct Dispatch<Action> {
+    dispatch: Arc<dyn Fn(Action) + Send + Sync>,
+}
+
+impl<Action> Clone for Dispatch<Action> {
+    fn clone(&self) -> Self {
+        Self {
+            dispatch: self.dispatch.clone(),
+        }
+    }
+}
+
+impl<Action> Dispatch<Action> {
+    pub fn dispatch(&self, action: Action) {
+        (self.dispatch)(action);
+    }
+}
+
+/// The reducer a dispatch uses is the one from the render it was made in.
+pub(crate) fn handle_reducer<
+    State: Send + Sync + Debug + 'static,
+    Action: Send + Sync + 'static,
+>(
+    ctx: &Context,
+    init: impl FnOnce() -> State,
+    reduce: impl Fn(&mut State, Action) + Send + Sync + 'static,
+) -> (Signal<State>, Dispatch<Action>) {
+    if !ctx.check_hook(HookSlot::new::<State>(HookKind::Reducer)) {
+        let dispatch = Dispatch {
+            dispatch: Arc::new(|_| {}),
+        };
+        return (ctx.detached_signal(init()), dispatch);
+    }
+    let (state, set_state) = state_slot(ctx, init);
+    let reduce = Arc::new(reduce);
+    let dispatch = Dispatch {
+        dispatch: Arc::new(move |action| {
+            let reduce = reduce.clone();
+            set_state.clone().mutate(move |state| reduce(state, action));
+        }),
+    };
+    (state, dispatch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Todos {
+        items: Vec<String>,
+        done: usize,
+    }
+
+    enum Action {
+        Add(&'static str),
+        Complete,
+        Clear,
+    }
+
+    fn reduce(todos: &mut Todos, action: Action) {
+        match action {
+            Action::Add(item) => todos.items.push(item.to_string()),
+            Action::Complete => {
+                todos.items.remove(0);
+                todos.done += 1;
+            }
+            Action::Clear => *todos = Todos::default(),
+        }
+    }
+
+    #[derive(Debug)]
+    struct TodoList;
+
+    impl StaticType for TodoList {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<TodoList>()
+        }
+    }
+
+    impl Component for TodoList {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (todos, dispatch) = ctx.reducer(Todos::default, reduce);
+            let summary = ctx.memo(|| {
+                log("summary");
+                todos.with(|todos| format!("{:?}, {} done", todos.items, todos.done))
+            });
+            ctx.render_with_event(
+                |actions: &Vec<fn() -> Action>| {
+                    for action in actions {
+                        dispatch.dispatch(action());
+                    }
+                },
+                |ctx| {
+                    (
+                        Native::Button {
+                            label: "Plan".to_string(),
+                            on_click: ctx.event(vec![
+                                || Action::Add("write"),
+                                || Action::Add("review"),
+                                || Action::Complete,
+                                || Action::Add("ship"),
+                            ]),
+                        },
+                        Native::Button {
+                            label: "Clear".to_string(),
+                            on_click: ctx.event(vec![|| Action::Clear]),
+                        },
+                        Native::Text { text: summary },
+                    )
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn actions_of_a_batch_are_reduced_in_order_before_one_update() {
+        let mut renderer = TestRenderer::new(TodoList);
+        renderer.get_by_text("[], 0 done");
+
+        renderer.click(&renderer.get_by_text("Plan"));
+
+        renderer.get_by_text("[\"review\", \"ship\"], 1 done");
+        assert_eq!(take_log(), ["summary", "summary"]);
+
+        renderer.click(&renderer.get_by_text("Clear"));
+        renderer.get_by_text("[], 0 done");
+    }
+}
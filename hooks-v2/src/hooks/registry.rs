@@ -0,0 +1,103 @@
+use super::*;
+use std::rc::Rc;
+
+/// Every mounted component by id, so events and state updates can reach
+/// their target without walking the tree. `mount_visit` registers a holder
+/// and `unmount_visit` removes it.
+#[derive(Default)]
+pub(crate) struct ComponentRegistry {
+    components: HashMap<usize, RegisteredComponent>,
+}
+
+struct RegisteredComponent {
+    holder: Rc<ComponentHolder>,
+    parent_id: Option<usize>,
+}
+
+impl ComponentRegistry {
+    pub(crate) fn register(&mut self, holder: Rc<ComponentHolder>, parent_id: Option<usize>) {
+        self.components.insert(
+            holder.component_instance.component_id,
+            RegisteredComponent { holder, parent_id },
+        );
+    }
+
+    pub(crate) fn unregister(&mut self, component_id: usize) {
+        self.components.remove(&component_id);
+    }
+
+    pub(crate) fn get(&self, component_id: usize) -> Result<Rc<ComponentHolder>, RuactError> {
+        self.components
+            .get(&component_id)
+            .map(|component| component.holder.clone())
+            .ok_or(RuactError::ComponentUnmounted { component_id })
+    }
+
+    pub(crate) fn ancestors(&self, component_id: usize) -> impl Iterator<Item = usize> + '_ {
+        let parent_id = |component_id: &usize| self.components.get(component_id)?.parent_id;
+        std::iter::successors(parent_id(&component_id), parent_id)
+    }
+}
+
+impl RuntimeState {
+    /// Looks a mounted component up by id. The registry borrow ends before
+    /// this returns, so the caller is free to run the component.
+    pub(crate) fn find_component_by_id(
+        &self,
+        component_id: usize,
+    ) -> Result<Rc<ComponentHolder>, RuactError> {
+        self.registry.borrow().get(component_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Toggle;
+
+    impl StaticType for Toggle {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Toggle>()
+        }
+    }
+
+    impl Component for Toggle {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (is_shown, _) = ctx.state(|| true);
+            ctx.render(|| is_shown.get().then_some(Adder))
+        }
+    }
+
+    #[test]
+    fn updates_to_an_unmounted_component_are_reported() {
+        let (runtime, root_holder) = mount(Toggle);
+        let counter_holder = root_holder.children.borrow()[0].clone();
+        let counter_id = counter_holder.component_instance.component_id;
+        assert!(runtime.find_component_by_id(counter_id).is_ok());
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, false)],
+        );
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![
+                set_state(&counter_holder, 0, 5),
+                event_item(&runtime, counter_id, 1),
+            ],
+        );
+
+        let unmounted = RuactError::ComponentUnmounted {
+            component_id: counter_id,
+        };
+        assert_eq!(
+            runtime.find_component_by_id(counter_id).err(),
+            Some(unmounted.clone())
+        );
+        assert_eq!(take_reported(), [unmounted.clone(), unmounted]);
+    }
+}
@@ -1,50 +1,48 @@
 use super::*;
 
-pub(crate) fn handle_render<'a, C: Component>(
-    ctx: &'a Context,
-    render: impl 'a + FnOnce() -> C,
-) -> Option<OnceCell<Box<dyn Component>>> {
+pub(crate) fn handle_render<R: IntoChildren>(
+    ctx: &Context,
+    render: impl FnOnce() -> R,
+) -> Option<Vec<Child>> {
     handle_render_internal(ctx, render)
 }
 
-pub(crate) fn handle_render_with_event<'a, C: Component, Event: 'static + Send + Sync>(
-    ctx: &'a Context,
-    render: impl FnOnce(EventContext<Event>) -> C,
-) -> Option<OnceCell<Box<dyn Component>>> {
+pub(crate) fn handle_render_with_event<R: IntoChildren, Event: 'static + Send + Sync>(
+    ctx: &Context,
+    render: impl FnOnce(EventContext<Event>) -> R,
+) -> Option<Vec<Child>> {
     handle_render_internal(ctx, || {
-        let event_context = EventContext::new(ctx.instance.component_id);
+        let event_context = EventContext::new(ctx.runtime.tx.clone(), ctx.instance.component_id);
         render(event_context)
     })
 }
 
-fn handle_render_internal<'a, C: Component>(
-    ctx: &'a Context,
-    render: impl 'a + FnOnce() -> C,
-) -> Option<OnceCell<Box<dyn Component>>> {
-    unsafe {
-        let is_first_run = || {
-            ctx.instance
-                .is_first_render
-                .swap(false, std::sync::atomic::Ordering::SeqCst)
-        };
+fn handle_render_internal<R: IntoChildren>(
+    ctx: &Context,
+    render: impl FnOnce() -> R,
+) -> Option<Vec<Child>> {
+    let is_first_run = || ctx.instance.is_first_render.replace(false);
 
-        let used_signal_updated = || {
-            let render_used_signals = ctx.instance.render_used_signals.as_ptr().as_ref().unwrap();
-            ctx.is_used_signal_updated(render_used_signals)
-        };
+    let used_signal_updated = || {
+        let render_used_signals = ctx.instance.render_used_signals.borrow();
+        ctx.is_used_signal_updated(render_used_signals.iter())
+    };
 
-        if is_first_run() || ctx.is_set_state_phase() && used_signal_updated() {
-            let child = render();
-            let used_signal_ids = take_used_signals();
-            let render_used_signals = ctx.instance.render_used_signals.as_ptr().as_mut().unwrap();
-            *render_used_signals = used_signal_ids;
+    if is_first_run()
+        || ctx.instance.is_props_changed.get()
+        || ctx.is_set_state_phase() && used_signal_updated()
+    {
+        let children = render().into_children();
+        ctx.runtime.take_used_signals(
+            Subscriber {
+                component_id: ctx.instance.component_id,
+                hook: TrackedHook::Render,
+            },
+            &mut ctx.instance.render_used_signals.borrow_mut(),
+        );
 
-            Some(
-                std::mem::transmute::<Box<dyn Component>, Box<dyn Component>>(Box::new(child))
-                    .into(),
-            )
-        } else {
-            None
-        }
+        Some(children)
+    } else {
+        None
     }
 }
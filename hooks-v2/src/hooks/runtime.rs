@@ -0,0 +1,351 @@
+use super::*;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+/// A mounted tree and the backend it renders to, returned by `start`.
+///
+/// The runtime only does work when asked to, so it can be driven by its own
+/// loop or embedded in a host's:
+///
+/// ```ignore
+/// let mut runtime = start(App, backend);
+/// loop {
+///     runtime.tick();
+/// }
+/// ```
+///
+/// Every runtime has its own queue, components and subscriptions, so any
+/// number of them can run side by side, on one thread or on several.
+/// Dropping the runtime shuts it down, like `shutdown`.
+pub struct Runtime<Backend: NativeBackend> {
+    state: Rc<RuntimeState>,
+    root_holder: Option<Rc<ComponentHolder>>,
+    backend: Backend,
+    rx: channel::Receiver,
+}
+
+/// What the hooks and the propagation of one runtime share, handed down
+/// through `Context`.
+pub(crate) struct RuntimeState {
+    pub(crate) tx: channel::Sender,
+    pub(crate) used_signal_ids: UsedSignals,
+    pub(crate) subscriptions: RefCell<SubscriptionIndex>,
+    pub(crate) registry: RefCell<ComponentRegistry>,
+    pub(crate) atoms: RefCell<AtomStore>,
+    pub(crate) native_tree: RefCell<NativeTree>,
+    pub(crate) error_reporter: RefCell<ErrorReporter>,
+    pub(crate) tasks: Rc<TaskSpawner>,
+    pub(crate) clock: Rc<dyn Clock>,
+    next_component_id: Cell<usize>,
+}
+
+impl RuntimeState {
+    /// Times timers with `clock`, or with the system time if there is none.
+    pub(crate) fn new(tx: channel::Sender, clock: Option<Rc<dyn Clock>>) -> Rc<Self> {
+        let tasks = Rc::new(TaskSpawner::new());
+        let clock = clock.unwrap_or_else(|| {
+            Rc::new(SystemClock {
+                tasks: tasks.clone(),
+            })
+        });
+        Rc::new(Self {
+            tx,
+            used_signal_ids: Default::default(),
+            subscriptions: Default::default(),
+            registry: Default::default(),
+            atoms: Default::default(),
+            native_tree: Default::default(),
+            error_reporter: RefCell::new(default_error_reporter()),
+            tasks,
+            clock,
+            next_component_id: Cell::new(0),
+        })
+    }
+
+    pub(crate) fn new_component_id(&self) -> usize {
+        next_index(&self.next_component_id)
+    }
+
+    /// A state without a `Runtime` around it, for tests that run batches by
+    /// hand. What its handles send is dropped.
+    #[cfg(test)]
+    pub(crate) fn detached() -> Rc<Self> {
+        Self::new(crossbeam::channel::unbounded().0, None)
+    }
+}
+
+/// Sends to a runtime from anywhere, for backends that report user
+/// interactions from their own thread or callbacks.
+#[derive(Clone)]
+pub struct RuntimeHandle {
+    tx: channel::Sender,
+}
+
+impl RuntimeHandle {
+    /// Fires an event on a native node the way a user interaction would, so
+    /// it propagates through the node's ancestors before the node's own
+    /// callback prop `name` gets it. Backends call this with the node ids
+    /// from the patches they were sent.
+    pub fn fire_native_event<Payload: Any + Send>(
+        &self,
+        node_id: usize,
+        name: &'static str,
+        payload: Payload,
+    ) {
+        channel::send(
+            &self.tx,
+            Item::NativeEvent {
+                node_id,
+                name,
+                payload: Box::new(payload),
+            },
+        );
+    }
+}
+
+impl<Backend: NativeBackend> Runtime<Backend> {
+    pub(crate) fn new(
+        component: Box<dyn Component>,
+        mut backend: Backend,
+        clock: Option<Rc<dyn Clock>>,
+    ) -> Self {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let state = RuntimeState::new(tx.clone(), clock);
+        backend.connect(RuntimeHandle { tx });
+        let root_holder = mount_root(&state, component);
+        flush_native_tree(&state, &root_holder, &mut backend);
+        Self {
+            state,
+            root_holder: Some(root_holder),
+            backend,
+            rx,
+        }
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut Backend {
+        &mut self.backend
+    }
+
+    pub fn handle(&self) -> RuntimeHandle {
+        RuntimeHandle {
+            tx: self.state.tx.clone(),
+        }
+    }
+
+    /// Replaces what this runtime does with the errors it recovers from, see
+    /// `RuactError`. The default reporter prints them to stderr.
+    pub fn set_error_reporter(&self, reporter: impl Fn(&RuactError) + 'static) {
+        *self.state.error_reporter.borrow_mut() = Rc::new(reporter);
+    }
+
+    /// Waits for the next update or event, then processes it together with
+    /// everything queued behind it as one batch and flushes the patches.
+    pub fn tick(&mut self) {
+        let Some(root_holder) = &self.root_holder else {
+            return;
+        };
+        let Ok(item) = self.rx.recv() else {
+            return;
+        };
+        run_batch(
+            &self.state,
+            root_holder,
+            std::iter::once(item).chain(self.rx.try_iter()),
+        );
+        self.flush();
+    }
+
+    /// Processes one batch of what is queued, without waiting. Returns
+    /// whether there was anything to process, so a host loop can tell when
+    /// the tree is settled.
+    pub fn poll(&mut self) -> bool {
+        let Some(root_holder) = &self.root_holder else {
+            return false;
+        };
+        let mut items = self.rx.try_iter().peekable();
+        if items.peek().is_none() {
+            return false;
+        }
+        run_batch(&self.state, root_holder, items);
+        self.flush();
+        true
+    }
+
+    /// Polls until nothing is queued anymore, including the updates that
+    /// effects and the backend queue while the batches run.
+    pub fn run_until_idle(&mut self) {
+        while self.poll() {}
+    }
+
+    /// Unmounts the tree, running every effect cleanup, and flushes the
+    /// removals to the backend. Whatever is still queued is dropped.
+    pub fn shutdown(mut self) {
+        self.unmount();
+    }
+
+    fn flush(&mut self) {
+        if let Some(root_holder) = &self.root_holder {
+            flush_native_tree(&self.state, root_holder, &mut self.backend);
+        }
+    }
+
+    fn unmount(&mut self) {
+        let Some(root_holder) = self.root_holder.take() else {
+            return;
+        };
+        unmount_visit(&self.state, root_holder.clone());
+        flush_native_tree(&self.state, &root_holder, &mut self.backend);
+        self.rx.try_iter().for_each(drop);
+    }
+}
+
+impl<Backend: NativeBackend> Drop for Runtime<Backend> {
+    fn drop(&mut self) {
+        self.unmount();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[derive(Debug)]
+    struct Greeting;
+
+    impl StaticType for Greeting {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Greeting>()
+        }
+    }
+
+    impl Component for Greeting {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (text, set_text) = ctx.state(|| "Hello".to_string());
+            ctx.effect("greet once", move || {
+                set_text.set("Hello, world".to_string());
+                || log("cleaned up")
+            });
+            ctx.render(|| Native::Text { text })
+        }
+    }
+
+    /// Keeps the patches where the test can still read them after the
+    /// runtime is gone.
+    #[derive(Default)]
+    struct Recorder(Rc<RefCell<Vec<Patch>>>);
+
+    impl NativeBackend for Recorder {
+        fn apply(&mut self, patch: Patch) {
+            self.0.borrow_mut().push(patch);
+        }
+    }
+
+    #[test]
+    fn polls_until_settled_and_shuts_down() {
+        let recorder = Recorder::default();
+        let patches = recorder.0.clone();
+        let mut runtime = start(Greeting, recorder);
+        let [Patch::CreateNode { node_id, .. }, ..] = patches.take()[..] else {
+            panic!("expected the text to be created first");
+        };
+
+        runtime.run_until_idle();
+        assert!(!runtime.poll());
+        assert_eq!(
+            patches.take(),
+            [
+                Patch::SetProp {
+                    node_id,
+                    prop: Prop::Text("Hello, world".to_string()),
+                },
+                Patch::Commit,
+            ]
+        );
+
+        runtime.shutdown();
+        assert_eq!(take_log(), ["cleaned up"]);
+        assert_eq!(
+            patches.take(),
+            [Patch::RemoveNode { node_id }, Patch::Commit]
+        );
+    }
+
+    #[derive(Debug)]
+    struct Clicker;
+
+    impl StaticType for Clicker {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Clicker>()
+        }
+    }
+
+    impl Component for Clicker {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (count, set_count) = ctx.state(|| 0);
+            ctx.render_with_event(
+                |_: &()| set_count.mutate(|count| *count += 1),
+                |ctx| Native::Button {
+                    label: format!("Clicked {}", *count.get()),
+                    on_click: ctx.event(()),
+                },
+            )
+        }
+    }
+
+    fn click_times(renderer: &mut TestRenderer, times: usize) {
+        for _ in 0..times {
+            let button = renderer.find_all_by_kind(NativeKind::Button).remove(0);
+            renderer.click(&button);
+        }
+    }
+
+    #[test]
+    fn runtimes_on_one_thread_are_independent() {
+        let mut first = TestRenderer::new(Clicker);
+        let mut second = TestRenderer::new(Clicker);
+
+        click_times(&mut first, 2);
+        click_times(&mut second, 1);
+
+        first.get_by_text("Clicked 2");
+        second.get_by_text("Clicked 1");
+    }
+
+    #[test]
+    fn runtimes_run_on_several_threads() {
+        let threads = (1..=4)
+            .map(|times| {
+                std::thread::spawn(move || {
+                    let mut renderer = TestRenderer::new(Clicker);
+                    click_times(&mut renderer, times);
+                    renderer.get_by_text(&format!("Clicked {}", times));
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn callbacks_reach_their_runtime_from_another_thread() {
+        let mut renderer = TestRenderer::new(Clicker);
+        let Some(Prop::OnClick(on_click)) =
+            renderer.get_by_text("Clicked 0").prop("on_click").cloned()
+        else {
+            panic!("the button has no on_click");
+        };
+
+        std::thread::spawn(move || on_click.call()).join().unwrap();
+        renderer.run_until_idle();
+
+        renderer.get_by_text("Clicked 1");
+    }
+}
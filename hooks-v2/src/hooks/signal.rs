@@ -1,14 +1,31 @@
-use std::{cell::RefCell, collections::HashSet};
+use super::*;
+use std::{
+    cell::{Ref, RefCell},
+    rc::Rc,
+};
 
-thread_local! {
-    pub(crate) static USED_SIGNAL_IDS: RefCell<HashSet<SignalId>> = RefCell::new(HashSet::new());
-}
+/// The signals read since the running hook started. Every signal of a
+/// runtime shares its set, so reading one is tracked without a `Context`.
+pub(crate) type UsedSignals = Rc<RefCell<HashSet<SignalId>>>;
+
+impl RuntimeState {
+    /// Takes the signals read since the last call into `signal_ids`,
+    /// replacing what it held, and records them as what `subscriber` depends
+    /// on from now on. The hook passes the `Vec` it kept from its last run,
+    /// so a re-run doesn't allocate.
+    pub(crate) fn take_used_signals(&self, subscriber: Subscriber, signal_ids: &mut Vec<SignalId>) {
+        signal_ids.clear();
+        signal_ids.extend(self.used_signal_ids.borrow_mut().drain());
+        self.subscriptions
+            .borrow_mut()
+            .track(subscriber, signal_ids);
+    }
 
-pub(crate) fn take_used_signals() -> Vec<SignalId> {
-    USED_SIGNAL_IDS.with(|ids| {
-        let mut ids = ids.borrow_mut();
-        ids.drain().collect()
-    })
+    /// Forgets the signals read outside of a hook, by event handlers, so
+    /// they aren't tracked as read by the next hook.
+    pub(crate) fn discard_used_signals(&self) {
+        self.used_signal_ids.borrow_mut().clear();
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -17,33 +34,74 @@ pub(crate) struct SignalId {
     pub signal_index: usize,
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct Signal<'a, T> {
+/// A handle to a state or memo value. It always reads the current value,
+/// so it can be passed down as a prop and stay up to date. Reads go
+/// through a `Ref` guard, see the aliasing model in `value.rs`.
+///
+/// Whatever reads a signal is subscribed to it, whichever component owns
+/// it: a render, memo or effect of a child that reads a signal passed down
+/// by its parent runs again exactly when that signal changes, whether the
+/// parent re-renders or not.
+pub struct Signal<T> {
     id: SignalId,
-    value: &'a T,
+    value: Rc<RefCell<T>>,
+    used_signal_ids: UsedSignals,
 }
 
-impl<'a, T> Signal<'a, T> {
-    pub(crate) fn new(value: &'a T, id: SignalId) -> Self {
-        Self { value, id }
+impl<T> Signal<T> {
+    pub(crate) fn new(value: Rc<RefCell<T>>, id: SignalId, used_signal_ids: UsedSignals) -> Self {
+        Self {
+            value,
+            id,
+            used_signal_ids,
+        }
     }
-    fn use_it(&self) {
-        USED_SIGNAL_IDS.with(|ids| {
-            let mut ids = ids.borrow_mut();
-            ids.insert(self.id);
-        });
+    /// Marks the signal as read by the hook that is running.
+    pub(crate) fn track(&self) {
+        self.used_signal_ids.borrow_mut().insert(self.id);
     }
-    pub fn on_effect(&'a self) -> bool {
-        self.use_it();
+    /// Subscribes the running hook to the signal without reading it, for
+    /// effects that only care that it changed. Always true, so it reads as
+    /// the condition the effect runs on.
+    pub fn on_effect(&self) -> bool {
+        self.track();
         true
     }
+
+    /// Reads the value and subscribes the running hook to it. Drop the
+    /// guard before the hook returns: the runtime can't update the value
+    /// while it is alive, and panics if it has to.
+    pub fn get(&self) -> Ref<'_, T> {
+        self.track();
+        self.value.borrow()
+    }
+
+    /// Like `get`, with the guard scoped to `read`.
+    pub fn with<R>(&self, read: impl FnOnce(&T) -> R) -> R {
+        read(&self.get())
+    }
+
+    /// Reads the value without subscribing anything to it.
+    pub(crate) fn peek(&self) -> Ref<'_, T> {
+        self.value.borrow()
+    }
 }
 
-impl<T> std::ops::Deref for Signal<'_, T> {
-    type Target = T;
+impl<T> Clone for Signal<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            value: self.value.clone(),
+            used_signal_ids: self.used_signal_ids.clone(),
+        }
+    }
+}
 
-    fn deref(&self) -> &Self::Target {
-        self.use_it();
-        self.value
+impl<T: Debug> Debug for Signal<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Signal")
+            .field("id", &self.id)
+            .field("value", &self.value)
+            .finish()
     }
 }
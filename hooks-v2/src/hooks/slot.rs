@@ -0,0 +1,116 @@
+use super::*;
+
+/// What one hook keeps between renders.
+pub(crate) enum Slot {
+    State(Rc<dyn ValueCell>),
+    Memo {
+        value: Rc<dyn ValueCell>,
+        used_signals: Vec<SignalId>,
+    },
+    Effect {
+        used_signals: Vec<SignalId>,
+        cleanup: Option<CleanupFn>,
+    },
+}
+
+impl Debug for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Slot::State(value) => f.debug_tuple("State").field(value).finish(),
+            Slot::Memo {
+                value,
+                used_signals,
+            } => f
+                .debug_struct("Memo")
+                .field("value", value)
+                .field("used_signals", used_signals)
+                .finish(),
+            Slot::Effect {
+                used_signals,
+                cleanup,
+            } => f
+                .debug_struct("Effect")
+                .field("used_signals", used_signals)
+                .field("has_cleanup", &cleanup.is_some())
+                .finish(),
+        }
+    }
+}
+
+/// The slots of one instance, in the order its hooks first claimed them.
+///
+/// Slots are pushed on the first render and only replaced in place after,
+/// so a re-render reaches its values without allocating, and a value is
+/// owned by its slot and the signals handed out for it, nothing else.
+/// Which kind a slot is was settled by `check_hook` before the hook got to
+/// it, so a slot of the wrong kind is a bug in the hook, not in the
+/// component.
+#[derive(Debug, Default)]
+pub(crate) struct SlotArena {
+    slots: Vec<Slot>,
+}
+
+impl SlotArena {
+    /// Whether the slot at `slot_index` was not claimed yet, which is the
+    /// case on the hook's first run.
+    pub(crate) fn is_vacant(&self, slot_index: usize) -> bool {
+        self.slots.len() <= slot_index
+    }
+
+    pub(crate) fn claim(&mut self, slot_index: usize, slot: Slot) {
+        assert_eq!(self.slots.len(), slot_index, "slots are claimed in order");
+        self.slots.push(slot);
+    }
+
+    /// The state at `slot_index`, if there is one. State updates name their
+    /// slot themselves, so one that doesn't point at a state is reported as
+    /// stale instead of trusted.
+    pub(crate) fn state(&self, slot_index: usize) -> Option<&Rc<dyn ValueCell>> {
+        match self.slots.get(slot_index)? {
+            Slot::State(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn memo(&self, slot_index: usize) -> (&Rc<dyn ValueCell>, &[SignalId]) {
+        match &self.slots[slot_index] {
+            Slot::Memo {
+                value,
+                used_signals,
+            } => (value, used_signals),
+            slot => unreachable!("slot {} is not a memo: {:?}", slot_index, slot),
+        }
+    }
+
+    pub(crate) fn memo_used_signals_mut(&mut self, slot_index: usize) -> &mut Vec<SignalId> {
+        match &mut self.slots[slot_index] {
+            Slot::Memo { used_signals, .. } => used_signals,
+            slot => unreachable!("slot {} is not a memo: {:?}", slot_index, slot),
+        }
+    }
+
+    pub(crate) fn effect_mut(
+        &mut self,
+        slot_index: usize,
+    ) -> (&mut Vec<SignalId>, &mut Option<CleanupFn>) {
+        match &mut self.slots[slot_index] {
+            Slot::Effect {
+                used_signals,
+                cleanup,
+            } => (used_signals, cleanup),
+            slot => unreachable!("slot {} is not an effect: {:?}", slot_index, slot),
+        }
+    }
+
+    /// Takes the pending cleanup of every effect, in the order the effects
+    /// were declared.
+    pub(crate) fn take_effect_cleanups(&mut self) -> Vec<CleanupFn> {
+        self.slots
+            .iter_mut()
+            .filter_map(|slot| match slot {
+                Slot::Effect { cleanup, .. } => cleanup.take(),
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -1,220 +1,432 @@
 use super::*;
+use std::{cell::RefCell, rc::Rc};
 
-pub fn start<T: Component + 'static>(component: T) {
-    channel::init();
-
-    let mut root_holder: ComponentHolder =
-        mount_visit(OnceCell::from(Box::new(component) as Box<dyn Component>));
-
-    println!("--- visit ---");
-    visit(&root_holder, &|holder| {
-        let component = holder.component.get().unwrap().as_ref();
-        if component.static_type_id() == TypeId::of::<Native>() {
-            let native = component.native();
-            match native {
-                Native::Button { on_click } => {
-                    on_click.call();
-                    on_click.call();
-                    on_click.call();
-                    on_click.call();
-                    on_click.call();
-                }
-            }
+/// Mounts the component and renders it to the backend. Nothing else happens
+/// until the returned `Runtime` is driven.
+pub fn start<Backend: NativeBackend>(
+    component: impl Component + 'static,
+    backend: Backend,
+) -> Runtime<Backend> {
+    Runtime::new(Box::new(component), backend, None)
+}
+
+/// Like `start`, with the timers of `ctx.interval` and `ctx.timeout` timed
+/// by `clock` instead of the system time.
+pub fn start_with_clock<Backend: NativeBackend>(
+    component: impl Component + 'static,
+    backend: Backend,
+    clock: impl Clock + 'static,
+) -> Runtime<Backend> {
+    Runtime::new(Box::new(component), backend, Some(Rc::new(clock)))
+}
+
+pub(crate) fn mount_root(
+    runtime: &Rc<RuntimeState>,
+    component: Box<dyn Component>,
+) -> Rc<ComponentHolder> {
+    let root_holder = mount_visit(
+        runtime,
+        Child {
+            key: None,
+            test_id: None,
+            component,
+        },
+        None,
+    );
+    runtime.native_tree.borrow_mut().mark_dirty(None);
+    root_holder
+}
+
+/// Settles the order of the native children that changed since the last
+/// flush and hands the backend every patch queued in the meantime.
+pub(crate) fn flush_native_tree(
+    runtime: &RuntimeState,
+    root_holder: &ComponentHolder,
+    backend: &mut impl NativeBackend,
+) {
+    let dirty = runtime.native_tree.borrow_mut().take_dirty();
+    for container in dirty {
+        let children = match container {
+            None => native_children(root_holder),
+            Some(node_id) => match runtime.find_component_by_id(node_id) {
+                Ok(holder) => native_children(&holder),
+                Err(_) => continue,
+            },
+        };
+        runtime
+            .native_tree
+            .borrow_mut()
+            .set_children(container, children);
+    }
+
+    let patches = runtime.native_tree.borrow_mut().take_patches();
+    for patch in patches {
+        backend.apply(patch);
+    }
+}
+
+/// Processes one batch of channel items and re-renders at most once.
+///
+/// A batch is the item that woke the runtime up plus everything already
+/// queued behind it, including items that event handlers enqueue while the
+/// batch is running. Items are handled in the order they were sent:
+/// - `SetStateItem`s are applied to the state storage right away, so later
+///   items in the same batch (a `mutate` after a `set`, or an event handler
+///   reading the state) see the updated value.
+/// - Events, from an `EventCallback` or a native node, are dispatched
+///   against the tree as it was last rendered.
+///
+/// Once the queue is empty, a single `set_state_propagation` runs with the
+/// union of every updated signal, so each component renders at most once per
+/// batch no matter how many of its states changed.
+pub(crate) fn run_batch(
+    runtime: &Rc<RuntimeState>,
+    root_holder: &ComponentHolder,
+    items: impl IntoIterator<Item = Item>,
+) {
+    let mut updated_signals = HashSet::new();
+
+    for item in items {
+        let result = match item {
+            Item::SetStateItem(set_state_item) => apply_set_state_item(runtime, set_state_item)
+                .map(|signal_id| {
+                    updated_signals.extend(signal_id);
+                }),
+            Item::Event {
+                event_callback,
+                payload,
+            } => dispatch_event(runtime, event_callback, payload),
+            Item::NativeEvent {
+                node_id,
+                name,
+                payload,
+            } => dispatch_native_event(runtime, node_id, name, payload),
+        };
+        if let Err(error) = result {
+            runtime.report_error(error);
         }
-    });
+    }
 
-    println!("--- visit done ---");
-    println!("root_holder: {:#?}", root_holder);
-
-    while let Ok(item) = channel::RX.get().unwrap().recv() {
-        println!("Channel Recv: {:#?}", item);
-        match item {
-            Item::SetStateItem(set_state_item) => {
-                let signal_id = match set_state_item {
-                    SetStateItem::Set { signal_id, .. } => signal_id,
-                    SetStateItem::Mutate { signal_id, .. } => signal_id,
-                };
-
-                let component = find_component_by_id(&root_holder, signal_id.component_id);
-                if let Some(component) = component {
-                    unsafe {
-                        let state_list = component
-                            .component_instance
-                            .state_list
-                            .as_ptr()
-                            .as_mut()
-                            .unwrap();
-
-                        let state = state_list.get_mut(signal_id.signal_index).unwrap();
-                        match set_state_item {
-                            SetStateItem::Set { value, .. } => {
-                                *state = value.into();
-                            }
-                            SetStateItem::Mutate { mutate, .. } => {
-                                // panic!
-                                let mut inner_state = std::mem::replace(state, Arc::new(()));
-                                let count = Arc::strong_count(&inner_state);
-                                println!("count: {}", count);
-                                let mut_state = Arc::get_mut(&mut inner_state).unwrap();
-                                mutate(mut_state);
-                                let _ = std::mem::replace(state, inner_state);
-                            }
-                        }
-                    };
-
-                    let updated_signals =
-                        Arc::new(AtomicCell::new(vec![signal_id].into_iter().collect()));
-
-                    set_state_propagation(&mut root_holder, updated_signals);
-                }
-            }
-            Item::EventCallback(event_callback) => {
-                let holder = find_component_by_id(&root_holder, event_callback.component_id);
-                if let Some(holder) = holder {
-                    let ctx = Context::new(
-                        ContextFor::Event { event_callback },
-                        holder.component_instance.clone(),
-                    );
-
-                    let ContextDone::NoRender = holder.component.get().unwrap().component(&ctx) else {
-                        unreachable!()
-                    };
-                }
-            }
+    if !updated_signals.is_empty() {
+        let updated_signals = Rc::new(RefCell::new(updated_signals));
+        let mut targets = PropagationTargets::default();
+        targets.refresh(runtime, &updated_signals.borrow());
+        if targets.should_visit(root_holder.component_instance.component_id) {
+            set_state_propagation(runtime, root_holder, updated_signals, &mut targets);
         }
-        println!("root_holder: {:#?}", root_holder);
     }
+}
 
-    fn set_state_propagation(
-        holder: &mut ComponentHolder,
-        updated_signals: Arc<AtomicCell<HashSet<SignalId>>>,
-    ) {
+/// The signal of the updated state, or `None` if the update left it as it
+/// was.
+fn apply_set_state_item(
+    runtime: &RuntimeState,
+    set_state_item: SetStateItem,
+) -> Result<Option<SignalId>, RuactError> {
+    let signal_id = set_state_item.signal_id();
+    let state_index = set_state_item.state_index();
+    let (owner_type_name, state) = match signal_id.component_id {
+        ATOM_COMPONENT_ID => ("atom", runtime.atom_state(state_index)?),
+        component_id => {
+            let component = runtime.find_component_by_id(component_id)?;
+            let instance = &component.component_instance;
+            let state = instance.slots.borrow().state(state_index).cloned().ok_or(
+                RuactError::StaleSignal {
+                    component_id,
+                    signal_index: signal_id.signal_index,
+                },
+            )?;
+            (instance.component_type_name, state)
+        }
+    };
+    let result = match set_state_item {
+        SetStateItem::Set { value, .. } => state.set(value).map(|()| true),
+        SetStateItem::Mutate { mutate, .. } => state.mutate(mutate),
+    };
+    let is_changed = result.map_err(|update_type_name| RuactError::StateTypeMismatch {
+        component_type_name: owner_type_name,
+        state_index,
+        state_type_name: state.type_name(),
+        update_type_name,
+    })?;
+
+    Ok(is_changed.then_some(signal_id))
+}
+
+pub(crate) fn dispatch_event(
+    runtime: &Rc<RuntimeState>,
+    event_callback: EventCallback,
+    payload: Box<dyn Any + Send>,
+) -> Result<(), RuactError> {
+    let holder = runtime.find_component_by_id(event_callback.component_id)?;
+    let component_type_name = holder.component_instance.component_type_name;
+    let event = event_callback
+        .resolve(payload)
+        .map_err(|expected_type_name| RuactError::EventPayloadMismatch {
+            component_type_name,
+            expected_type_name,
+        })?;
+    let ctx = Context::new(
+        runtime.clone(),
+        ContextFor::Event { event },
+        holder.component_instance.clone(),
+    );
+    let done = run_component(holder.component.borrow().as_ref(), &ctx);
+    runtime.discard_used_signals();
+    match done {
+        ContextDone::NoRender => Ok(()),
+        ContextDone::Rendered { .. } => Err(RuactError::RenderedDuringEvent {
+            component_type_name,
+        }),
+    }
+}
+
+/// Re-runs the components subscribed to the updated signals and the
+/// children whose parent re-rendered them with new props. Subtrees that
+/// contain neither are skipped, and components that are only on the way to
+/// one are walked through without being run.
+fn set_state_propagation(
+    runtime: &Rc<RuntimeState>,
+    holder: &ComponentHolder,
+    updated_signals: Rc<RefCell<HashSet<SignalId>>>,
+    targets: &mut PropagationTargets,
+) {
+    let component_id = holder.component_instance.component_id;
+    if holder.component_instance.is_props_changed.get() || targets.is_subscribed(component_id) {
         let ctx = Context::new(
+            runtime.clone(),
             ContextFor::SetState {
                 updated_signals: updated_signals.clone(),
             },
             holder.component_instance.clone(),
         );
-        let done = holder.component.get().unwrap().component(&ctx);
-        match done {
-            ContextDone::Rendered { child } => {
-                let child_object = child.get().unwrap().as_ref();
-                let component_type_id = StaticType::static_type_id(child_object);
-                let prev_type_id = holder.component.get().unwrap().as_ref().static_type_id();
-
-                if prev_type_id != component_type_id {
-                    let component_instance = Arc::new(ComponentInstance::new(
-                        new_component_id(),
-                        component_type_id,
-                        child_object.static_type_name(),
-                    ));
-
-                    let child_holder = ComponentHolder {
-                        component: child,
-                        component_instance,
-                        children: AtomicCell::new(vec![]),
-                    };
-                    holder.children.store(vec![child_holder]);
-                }
+        let done = run_component(holder.component.borrow().as_ref(), &ctx);
+        holder.component_instance.is_props_changed.set(false);
+        if let ContextDone::Rendered { children } = done {
+            if holder.is_native() {
+                let props = native_props(
+                    holder.component.borrow().native(),
+                    holder.test_id.borrow().as_deref(),
+                );
+                runtime.native_tree.borrow_mut().update(component_id, props);
             }
-            _ => {}
-        }
-
-        for child in unsafe { holder.children.as_ptr().as_mut().unwrap() } {
-            set_state_propagation(child, updated_signals.clone())
+            reconcile_children(runtime, holder, children);
         }
+        targets.refresh(runtime, &updated_signals.borrow());
     }
 
-    fn find_component_by_id<'a>(
-        root: &'a ComponentHolder,
-        component_id: usize,
-    ) -> Option<&'a ComponentHolder> {
-        find_component(root, &|holder| {
-            holder.component_instance.component_id == component_id
-        })
+    let children = holder.children.borrow().clone();
+    for child in &children {
+        if child.component_instance.is_props_changed.get()
+            || targets.should_visit(child.component_instance.component_id)
+        {
+            set_state_propagation(runtime, child, updated_signals.clone(), targets)
+        }
     }
+}
 
-    fn find_component<'a>(
-        holder: &'a ComponentHolder,
-        find: &impl Fn(&ComponentHolder) -> bool,
-    ) -> Option<&'a ComponentHolder> {
-        if find(holder) {
-            Some(holder)
-        } else {
-            match holder {
-                ComponentHolder {
-                    component: _,
-                    component_instance: _,
-                    children,
-                } => {
-                    for child in unsafe { children.as_ptr().as_ref().unwrap() } {
-                        if let Some(component) = find_component(child, find) {
-                            return Some(component);
-                        }
-                    }
-                    None
-                }
+/// Matches rendered children to the previous ones. Keyed children are
+/// matched by key wherever they moved, unkeyed children by their order
+/// among the unkeyed siblings, where a `None` counts as one. A match only counts if the type is
+/// unchanged; the holder and its `ComponentInstance` are then kept, and the
+/// freshly rendered props replace the old ones. Since props can't be
+/// compared, a matched child always counts as having new props and is
+/// re-rendered by the rest of the propagation pass. Everything else is
+/// mounted from scratch, and previous holders that found no match are
+/// unmounted.
+fn reconcile_children(runtime: &Rc<RuntimeState>, holder: &ComponentHolder, children: Vec<Child>) {
+    // Matched holders are taken out, so what is left unmounts in the order
+    // it rendered.
+    let mut prev_children = holder
+        .children
+        .take()
+        .into_iter()
+        .map(Some)
+        .collect::<Vec<_>>();
+    let mut prev_keyed = HashMap::new();
+    let mut prev_unkeyed = VecDeque::new();
+    for (index, prev) in prev_children.iter().flatten().enumerate() {
+        // Duplicates were reported when they rendered, and only the first
+        // holder of a key is matched by it.
+        match &prev.key {
+            Some(key) if !prev_keyed.contains_key(key) => {
+                prev_keyed.insert(key.clone(), index);
             }
+            _ => prev_unkeyed.push_back(index),
         }
     }
 
-    fn visit(holder: &ComponentHolder, on_component: &impl Fn(&ComponentHolder)) {
-        on_component(holder);
-        match holder {
-            ComponentHolder {
-                component: _,
-                component_instance: _,
-                children,
-            } => {
-                for child in unsafe { children.as_ptr().as_ref().unwrap() } {
-                    visit(child, on_component);
-                }
-            }
-        }
+    let component_type_name = holder.component_instance.component_type_name;
+    let mut seen_keys = HashSet::new();
+    let matched_children = children
+        .into_iter()
+        .map(|child| {
+            let index = match first_key(runtime, component_type_name, &mut seen_keys, &child) {
+                Some(key) => prev_keyed.remove(key),
+                None => prev_unkeyed.pop_front(),
+            };
+            let Some(index) = index.filter(|&index| {
+                prev_children[index].as_ref().is_some_and(|prev| {
+                    prev.component_instance.component_type_id == child.component.static_type_id()
+                })
+            }) else {
+                return Err(child);
+            };
+            let prev = prev_children[index].take().unwrap();
+            *prev.component.borrow_mut() = child.component;
+            *prev.test_id.borrow_mut() = child.test_id;
+            prev.component_instance.is_props_changed.set(true);
+            Ok(prev)
+        })
+        .collect::<Vec<_>>();
+
+    for prev in prev_children.into_iter().flatten() {
+        unmount_visit(runtime, prev);
     }
 
-    fn mount_visit(component: OnceCell<Box<dyn Component>>) -> ComponentHolder {
-        let component_id = new_component_id();
-        let component_object = component.get().unwrap().as_ref();
-        let component_type_id = component_object.static_type_id();
-        let component_type_name = component_object.static_type_name();
-        let component_instance = Arc::new(ComponentInstance::new(
-            component_id,
-            component_type_id,
+    let next_children = matched_children
+        .into_iter()
+        .map(|matched| match matched {
+            Ok(prev) => prev,
+            Err(child) => mount_visit(runtime, child, Some(holder.component_instance.component_id)),
+        })
+        .collect();
+    holder.children.replace(next_children);
+
+    let container = native_container(runtime, holder.component_instance.component_id);
+    runtime.native_tree.borrow_mut().mark_dirty(container);
+}
+
+/// Tears a subtree down, children first, running each component's
+/// effect cleanups on the way up.
+pub(crate) fn unmount_visit(runtime: &RuntimeState, holder: Rc<ComponentHolder>) {
+    for child in holder.children.take() {
+        unmount_visit(runtime, child);
+    }
+    let component_id = holder.component_instance.component_id;
+    holder.component_instance.run_effect_cleanups();
+    if holder.is_native() {
+        runtime.native_tree.borrow_mut().remove(component_id);
+    }
+    runtime.subscriptions.borrow_mut().unmount(component_id);
+    runtime.registry.borrow_mut().unregister(component_id);
+}
+
+/// The key of `child` if no sibling before it had the same one. A repeated
+/// key is reported and the child is treated as unkeyed.
+fn first_key<'a>(
+    runtime: &RuntimeState,
+    component_type_name: &'static str,
+    seen_keys: &mut HashSet<Key>,
+    child: &'a Child,
+) -> Option<&'a Key> {
+    let key = child.key.as_ref()?;
+    if seen_keys.insert(key.clone()) {
+        Some(key)
+    } else {
+        runtime.report_error(RuactError::DuplicateKey {
             component_type_name,
-        ));
+            key: key.clone(),
+        });
+        None
+    }
+}
 
-        let context = Context::new(ContextFor::Mount, component_instance.clone());
+fn mount_visit(
+    runtime: &Rc<RuntimeState>,
+    child: Child,
+    parent_id: Option<usize>,
+) -> Rc<ComponentHolder> {
+    let Child {
+        key,
+        test_id,
+        component,
+    } = child;
+    let component_id = runtime.new_component_id();
+    let component_type_id = component.static_type_id();
+    let component_type_name = component.static_type_name();
+    let component_instance = Rc::new(ComponentInstance::new(
+        component_id,
+        parent_id,
+        component_type_id,
+        component_type_name,
+    ));
 
-        let done = component.get().unwrap().component(&context);
+    let context = Context::new(
+        runtime.clone(),
+        ContextFor::Mount,
+        component_instance.clone(),
+    );
 
-        ComponentHolder {
-            component,
-            component_instance,
-            children: AtomicCell::new(match done {
-                ContextDone::Rendered { child } => vec![mount_visit(child.into())],
-                ContextDone::NoRender => vec![],
-            }),
+    let done = run_component(component.as_ref(), &context);
+    if component_type_id == TypeId::of::<Native>() {
+        let native = component.native();
+        let props = native_props(native, test_id.as_deref());
+        runtime
+            .native_tree
+            .borrow_mut()
+            .create(component_id, native.kind(), props);
+    }
+
+    // Registered before the children mount, so they can look up their
+    // ancestors while they run.
+    let holder = Rc::new(ComponentHolder {
+        key,
+        test_id: RefCell::new(test_id),
+        component: RefCell::new(component),
+        component_instance,
+        children: RefCell::new(vec![]),
+    });
+    runtime
+        .registry
+        .borrow_mut()
+        .register(holder.clone(), parent_id);
+    if let ContextDone::Rendered { children } = done {
+        let mut seen_keys = HashSet::new();
+        for child in &children {
+            first_key(runtime, component_type_name, &mut seen_keys, child);
         }
+        let children = children
+            .into_iter()
+            .map(|child| mount_visit(runtime, child, Some(component_id)))
+            .collect();
+        holder.children.replace(children);
     }
+    holder
 }
 
-fn new_component_id() -> usize {
-    static COMPONENT_ID: AtomicUsize = AtomicUsize::new(0);
-    COMPONENT_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+/// Runs a component and checks that it called the same hooks as on its
+/// first render. `Context::render` checks this already; this catches a
+/// component that returned without rendering.
+fn run_component(component: &dyn Component, ctx: &Context) -> ContextDone {
+    let done = component.component(ctx);
+    if !ctx.check_hook_count() {
+        ctx.runtime.discard_used_signals();
+    }
+    done
 }
 
-struct ComponentHolder {
-    component: OnceCell<Box<dyn Component>>,
-    component_instance: Arc<ComponentInstance>,
-    children: AtomicCell<Vec<ComponentHolder>>,
+pub(crate) struct ComponentHolder {
+    pub(crate) key: Option<Key>,
+    pub(crate) test_id: RefCell<Option<String>>,
+    pub(crate) component: RefCell<Box<dyn Component>>,
+    pub(crate) component_instance: Rc<ComponentInstance>,
+    pub(crate) children: RefCell<Vec<Rc<ComponentHolder>>>,
+}
+
+impl ComponentHolder {
+    pub(crate) fn is_native(&self) -> bool {
+        self.component_instance.component_type_id == TypeId::of::<Native>()
+    }
 }
 
 impl Debug for ComponentHolder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ComponentHolder")
+            .field("key", &self.key)
+            .field("test_id", &self.test_id.borrow())
             .field("component_instance", &self.component_instance)
-            .field("children", unsafe { &self.children.as_ptr().as_ref() })
+            .field("children", &self.children.borrow())
             .finish()
     }
 }
@@ -235,3 +447,282 @@ Root에서부터 signal을 subscribe한 컴포넌트를 찾아나간다.
 컴포넌트 내 signal subscriber를 찾아서 재실행해준다.
 참고로, set_state는 곧장 실행되지 않는다. 다음 OnSignal tick때 진행한다.
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct ThreeStates;
+
+    impl StaticType for ThreeStates {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<ThreeStates>()
+        }
+    }
+
+    impl Component for ThreeStates {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (a, _) = ctx.state(|| 0);
+            let (b, _) = ctx.state(|| 0);
+            let (c, _) = ctx.state(|| 0);
+
+            ctx.render(|| log(format!("{} {} {}", *a.get(), *b.get(), *c.get())))
+        }
+    }
+
+    #[test]
+    fn batch_renders_once_with_every_update_applied() {
+        let (runtime, root_holder) = mount(ThreeStates);
+        assert_eq!(take_log(), ["0 0 0"]);
+
+        let items = vec![
+            set_state(&root_holder, 0, 1),
+            set_state(&root_holder, 1, 2),
+            mutate_state(&root_holder, 2, |count: &mut i32| *count += 3),
+        ];
+        run_batch(&runtime, &root_holder, items);
+
+        assert_eq!(take_log(), ["1 2 3"]);
+    }
+
+    #[test]
+    fn batch_applies_updates_to_the_same_state_in_order() {
+        let (runtime, root_holder) = mount(ThreeStates);
+
+        let items = vec![
+            mutate_state(&root_holder, 0, |count: &mut i32| *count += 1),
+            set_state(&root_holder, 0, 10),
+            mutate_state(&root_holder, 0, |count: &mut i32| *count += 5),
+        ];
+        run_batch(&runtime, &root_holder, items);
+
+        assert_eq!(take_log(), ["0 0 0", "15 0 0"]);
+    }
+
+    #[test]
+    fn empty_batch_does_not_render() {
+        let (runtime, root_holder) = mount(ThreeStates);
+        run_batch(&runtime, &root_holder, vec![]);
+
+        assert_eq!(take_log(), ["0 0 0"]);
+    }
+
+    #[derive(Debug)]
+    struct List;
+
+    impl StaticType for List {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<List>()
+        }
+    }
+
+    impl Component for List {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (keys, _) = ctx.state(|| vec![1, 2, 3]);
+            ctx.render(|| {
+                keys.get()
+                    .iter()
+                    .map(|&key| keyed(key, ListItem { key }))
+                    .collect::<Vec<_>>()
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct ListItem {
+        key: i32,
+    }
+
+    impl StaticType for ListItem {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<ListItem>()
+        }
+    }
+
+    impl Component for ListItem {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let key = self.key;
+            let (mounted_as, _) = ctx.state(|| key);
+            ctx.effect("record unmount", || move || log(format!("unmount {key}")));
+            ctx.render(|| log(format!("{} {}", key, *mounted_as.get())))
+        }
+    }
+
+    #[test]
+    fn keyed_children_keep_their_state_when_reordered() {
+        let (runtime, root_holder) = mount(List);
+        assert_eq!(take_log(), ["1 1", "2 2", "3 3"]);
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, vec![3, 1, 2])],
+        );
+
+        assert_eq!(take_log(), ["3 3", "1 1", "2 2"]);
+    }
+
+    #[test]
+    fn children_with_a_duplicate_key_are_reported_and_unmounted() {
+        let (runtime, root_holder) = mount(List);
+        take_log();
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, vec![1, 1])],
+        );
+        let duplicates = root_holder
+            .children
+            .borrow()
+            .iter()
+            .map(|child| child.component_instance.component_id)
+            .collect::<Vec<_>>();
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(
+            take_reported(),
+            [RuactError::DuplicateKey {
+                component_type_name: root_holder.component_instance.component_type_name,
+                key: Key::Int(1),
+            }]
+        );
+        take_log();
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, Vec::<i32>::new())],
+        );
+
+        assert_eq!(take_log(), ["unmount 1", "unmount 1"]);
+        for component_id in duplicates {
+            assert!(runtime.registry.borrow().get(component_id).is_err());
+        }
+    }
+
+    #[derive(Debug)]
+    struct Greeting;
+
+    impl StaticType for Greeting {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Greeting>()
+        }
+    }
+
+    impl Component for Greeting {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (name, _) = ctx.state(|| "a".to_string());
+            ctx.render(|| Label {
+                text: name.get().clone(),
+            })
+        }
+    }
+
+    /// Takes its text as a plain prop, so it only sees a new one if the
+    /// parent's render reaches it.
+    #[derive(Debug)]
+    struct Label {
+        text: String,
+    }
+
+    impl StaticType for Label {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Label>()
+        }
+    }
+
+    impl Component for Label {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (mounted_with, _) = ctx.state(|| self.text.clone());
+            ctx.render(|| log(format!("{} {}", *mounted_with.get(), self.text)))
+        }
+    }
+
+    #[test]
+    fn kept_child_renders_with_its_new_props() {
+        let (runtime, root_holder) = mount(Greeting);
+        let label_id = root_holder.children.borrow()[0]
+            .component_instance
+            .component_id;
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, "b".to_string())],
+        );
+
+        assert_eq!(take_log(), ["a a", "a b"]);
+        let label_holder = root_holder.children.borrow()[0].clone();
+        assert_eq!(label_holder.component_instance.component_id, label_id);
+    }
+
+    #[derive(Debug)]
+    struct Page;
+
+    impl StaticType for Page {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Page>()
+        }
+    }
+
+    impl Component for Page {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (is_banner_shown, _) = ctx.state(|| true);
+            ctx.render(|| (is_banner_shown.get().then_some(Banner), Tally))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Banner;
+
+    impl StaticType for Banner {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Banner>()
+        }
+    }
+
+    impl Component for Banner {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            ctx.render(|| ())
+        }
+    }
+
+    #[derive(Debug)]
+    struct Tally;
+
+    impl StaticType for Tally {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Tally>()
+        }
+    }
+
+    impl Component for Tally {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (count, _) = ctx.state(|| 0);
+            ctx.render(|| log(format!("tally {}", *count.get())))
+        }
+    }
+
+    #[test]
+    fn toggling_an_optional_child_keeps_the_state_of_the_next_sibling() {
+        let (runtime, root_holder) = mount(Page);
+        let tally_holder = root_holder.children.borrow()[1].clone();
+        run_batch(&runtime, &root_holder, vec![set_state(&tally_holder, 0, 1)]);
+        take_log();
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, false)],
+        );
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![set_state(&root_holder, 0, true)],
+        );
+
+        assert_eq!(take_log(), ["tally 1", "tally 1"]);
+        assert!(Rc::ptr_eq(&root_holder.children.borrow()[1], &tally_holder));
+    }
+}
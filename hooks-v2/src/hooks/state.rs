@@ -1,95 +1,178 @@
 use super::*;
 
+/// Mutates a state in place and tells whether it changed, failing with the
+/// type name of the state it expected if the value is of another type.
+pub(crate) type MutateFn =
+    Box<dyn FnOnce(&mut dyn Value) -> Result<bool, &'static str> + Send + Sync>;
+
 pub(crate) enum SetStateItem {
     Set {
         signal_id: SignalId,
+        state_index: usize,
         value: Box<dyn Value>,
     },
     Mutate {
         signal_id: SignalId,
-        mutate: Box<dyn FnOnce(&mut (dyn Value)) + Send + Sync>,
+        state_index: usize,
+        mutate: MutateFn,
     },
 }
 
+impl SetStateItem {
+    pub(crate) fn signal_id(&self) -> SignalId {
+        match self {
+            SetStateItem::Set { signal_id, .. } | SetStateItem::Mutate { signal_id, .. } => {
+                *signal_id
+            }
+        }
+    }
+
+    pub(crate) fn state_index(&self) -> usize {
+        match self {
+            SetStateItem::Set { state_index, .. } | SetStateItem::Mutate { state_index, .. } => {
+                *state_index
+            }
+        }
+    }
+}
+
 impl Debug for SetStateItem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SetStateItem::Set { signal_id, value } => {
+            SetStateItem::Set {
+                signal_id, value, ..
+            } => {
                 write!(
                     f,
                     "SetStateItem::Set {{ signal_id: {:?}, value: {:?} }}",
                     signal_id, value
                 )
             }
-            SetStateItem::Mutate {
-                signal_id,
-                mutate: _,
-            } => {
+            SetStateItem::Mutate { signal_id, .. } => {
                 write!(f, "SetStateItem::Mutate {{ signal_id: {:?} }}", signal_id,)
             }
         }
     }
 }
 
+/// Queues updates for a state. Nothing changes immediately: every update
+/// queued before the runtime wakes up is applied in order, and then the tree
+/// re-renders once for the whole batch.
 pub struct SetState<State: 'static + Debug + Send + Sync> {
+    tx: channel::Sender,
     signal_id: SignalId,
+    state_index: usize,
     _state: std::marker::PhantomData<State>,
 }
 
+impl<State: 'static + Debug + Send + Sync> Clone for SetState<State> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            signal_id: self.signal_id,
+            state_index: self.state_index,
+            _state: std::marker::PhantomData,
+        }
+    }
+}
+
 impl<State: 'static + Debug + Send + Sync> SetState<State> {
+    pub(crate) fn new(tx: channel::Sender, signal_id: SignalId, state_index: usize) -> Self {
+        Self {
+            tx,
+            signal_id,
+            state_index,
+            _state: std::marker::PhantomData,
+        }
+    }
+
     pub fn set(self, state: State) {
-        channel::send(channel::Item::SetStateItem(SetStateItem::Set {
-            signal_id: self.signal_id,
-            value: Box::new(state),
-        }));
+        channel::send(
+            &self.tx,
+            Item::SetStateItem(SetStateItem::Set {
+                signal_id: self.signal_id,
+                state_index: self.state_index,
+                value: Box::new(state),
+            }),
+        );
     }
     pub fn mutate(self, mutate: impl FnOnce(&mut State) + Send + Sync + 'static) {
-        channel::send(channel::Item::SetStateItem(SetStateItem::Mutate {
-            signal_id: self.signal_id,
-            mutate: Box::new(move |state| {
-                let state = state.as_any_mut().downcast_mut::<State>().unwrap();
-                println!("mutate before: {:?}", state);
-                mutate(state);
-                println!("mutate after: {:?}", state);
+        channel::send(
+            &self.tx,
+            Item::SetStateItem(SetStateItem::Mutate {
+                signal_id: self.signal_id,
+                state_index: self.state_index,
+                mutate: Box::new(move |state| {
+                    let state = state
+                        .as_any_mut()
+                        .downcast_mut::<State>()
+                        .ok_or(std::any::type_name::<State>())?;
+                    mutate(state);
+                    Ok(true)
+                }),
             }),
-        }));
+        );
     }
 }
 
-pub(crate) fn handle_state<'a, State: Send + Sync + Debug + 'static>(
-    context: &'a Context,
-    init: impl FnOnce() -> State,
-) -> (Signal<'a, State>, SetState<State>) {
-    unsafe {
-        let state_list = context.instance.state_list.as_ptr().as_mut().unwrap();
-        let state_index = context
-            .state_index
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-
-        let no_state = state_list.len() <= state_index;
+impl<State: 'static + Debug + Send + Sync + PartialEq> SetState<State> {
+    /// Like `set`, but if the state already equals `state` nothing that
+    /// read it runs again.
+    pub fn set_if_changed(self, state: State) {
+        channel::send(
+            &self.tx,
+            Item::SetStateItem(SetStateItem::Mutate {
+                signal_id: self.signal_id,
+                state_index: self.state_index,
+                mutate: Box::new(move |prev| {
+                    let prev = prev
+                        .as_any_mut()
+                        .downcast_mut::<State>()
+                        .ok_or(std::any::type_name::<State>())?;
+                    if *prev == state {
+                        return Ok(false);
+                    }
+                    *prev = state;
+                    Ok(true)
+                }),
+            }),
+        );
+    }
+}
 
-        if no_state {
-            let state = init();
+pub(crate) fn handle_state<State: Send + Sync + Debug + 'static>(
+    ctx: &Context,
+    init: impl FnOnce() -> State,
+) -> (Signal<State>, SetState<State>) {
+    if !ctx.check_hook(HookSlot::new::<State>(HookKind::State)) {
+        return (ctx.detached_signal(init()), ctx.detached_set_state());
+    }
+    state_slot(ctx, init)
+}
 
-            state_list.push(Arc::new(state));
-        }
+/// The storage of `state`, for the hooks built on it.
+pub(crate) fn state_slot<State: Send + Sync + Debug + 'static>(
+    ctx: &Context,
+    init: impl FnOnce() -> State,
+) -> (Signal<State>, SetState<State>) {
+    let state_index = next_index(&ctx.slot_index);
 
-        let state = &*(Arc::as_ptr(state_list.get(state_index).unwrap()) as *const State);
+    let no_state = ctx.instance.slots.borrow().is_vacant(state_index);
+    if no_state {
+        let state = init();
+        ctx.instance
+            .slots
+            .borrow_mut()
+            .claim(state_index, Slot::State(Rc::new(RefCell::new(state))));
+    }
 
-        let signal_id = SignalId {
-            component_id: context.instance.component_id,
-            signal_index: context
-                .signal_index
-                .fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-        };
+    let state =
+        downcast_value_cell::<State>(ctx.instance.slots.borrow().state(state_index).unwrap());
+    let signal_id = ctx.next_signal_id();
 
-        let set_state = SetState {
-            signal_id,
-            _state: std::marker::PhantomData,
-        };
+    let set_state = SetState::new(ctx.runtime.tx.clone(), signal_id, state_index);
 
-        let signal = Signal::new(state, signal_id);
+    let signal = Signal::new(state, signal_id, ctx.runtime.used_signal_ids.clone());
 
-        (signal, set_state)
-    }
+    (signal, set_state)
 }
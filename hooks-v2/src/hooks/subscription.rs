@@ -0,0 +1,374 @@
+use super::*;
+
+/// The hook that read a set of signals. Each one is re-tracked from scratch
+/// every time it runs.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum TrackedHook {
+    Render,
+    Memo { slot_index: usize },
+    Effect { slot_index: usize },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct Subscriber {
+    pub component_id: usize,
+    pub hook: TrackedHook,
+}
+
+/// Reverse index from a signal to the components whose render, memos or
+/// effects read it last time they ran.
+#[derive(Default, Debug)]
+pub(crate) struct SubscriptionIndex {
+    /// signal -> component id -> how many hooks of that component read it
+    subscribers: HashMap<SignalId, HashMap<usize, usize>>,
+    /// component id -> hook -> signals that hook read
+    subscriptions: HashMap<usize, HashMap<TrackedHook, Vec<SignalId>>>,
+}
+
+impl SubscriptionIndex {
+    pub(crate) fn unmount(&mut self, component_id: usize) {
+        for (_, signal_ids) in self.subscriptions.remove(&component_id).unwrap_or_default() {
+            Self::unsubscribe(&mut self.subscribers, component_id, &signal_ids);
+        }
+    }
+
+    /// Replaces whatever `subscriber` read on its previous run. The new
+    /// signals are counted before the previous ones are let go, so a signal
+    /// read on both runs keeps its entry, and the hook's list is refilled in
+    /// place: tracking the same reads again doesn't allocate.
+    pub(crate) fn track(&mut self, subscriber: Subscriber, signal_ids: &[SignalId]) {
+        for signal_id in signal_ids {
+            *self
+                .subscribers
+                .entry(*signal_id)
+                .or_default()
+                .entry(subscriber.component_id)
+                .or_default() += 1;
+        }
+        let tracked = self
+            .subscriptions
+            .entry(subscriber.component_id)
+            .or_default()
+            .entry(subscriber.hook)
+            .or_default();
+        Self::unsubscribe(&mut self.subscribers, subscriber.component_id, tracked);
+        tracked.clear();
+        tracked.extend_from_slice(signal_ids);
+    }
+
+    fn unsubscribe(
+        subscribers: &mut HashMap<SignalId, HashMap<usize, usize>>,
+        component_id: usize,
+        signal_ids: &[SignalId],
+    ) {
+        for signal_id in signal_ids {
+            let Some(components) = subscribers.get_mut(signal_id) else {
+                continue;
+            };
+            if let Some(count) = components.get_mut(&component_id) {
+                *count -= 1;
+                if *count == 0 {
+                    components.remove(&component_id);
+                }
+            }
+            if components.is_empty() {
+                subscribers.remove(signal_id);
+            }
+        }
+    }
+
+    pub(crate) fn subscribers<'a>(
+        &'a self,
+        signal_ids: impl IntoIterator<Item = &'a SignalId> + 'a,
+    ) -> impl Iterator<Item = usize> + 'a {
+        signal_ids
+            .into_iter()
+            .filter_map(|signal_id| self.subscribers.get(signal_id))
+            .flat_map(|components| components.keys().copied())
+    }
+}
+
+/// The components a propagation pass has to reach: the ones subscribed to an
+/// updated signal, and their ancestors which are only walked through.
+/// Memos add their own signal to the updated set while the pass runs, so the
+/// targets are recomputed whenever that set grew.
+#[derive(Default)]
+pub(crate) struct PropagationTargets {
+    seen_signal_count: usize,
+    subscribed: HashSet<usize>,
+    on_path: HashSet<usize>,
+}
+
+impl PropagationTargets {
+    pub(crate) fn refresh(&mut self, runtime: &RuntimeState, updated_signals: &HashSet<SignalId>) {
+        if self.seen_signal_count == updated_signals.len() {
+            return;
+        }
+        self.seen_signal_count = updated_signals.len();
+
+        let index = runtime.subscriptions.borrow();
+        let registry = runtime.registry.borrow();
+        for component_id in index.subscribers(updated_signals) {
+            if self.subscribed.insert(component_id) {
+                self.on_path.extend(registry.ancestors(component_id));
+            }
+        }
+    }
+
+    pub(crate) fn is_subscribed(&self, component_id: usize) -> bool {
+        self.subscribed.contains(&component_id)
+    }
+
+    pub(crate) fn should_visit(&self, component_id: usize) -> bool {
+        self.subscribed.contains(&component_id) || self.on_path.contains(&component_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Parent;
+
+    impl StaticType for Parent {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Parent>()
+        }
+    }
+
+    impl Component for Parent {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (count, set_count) = ctx.state(|| 0);
+            let parity =
+                ctx.memo(|| if *count.get() % 2 == 0 { "even" } else { "odd" }.to_string());
+            ctx.render_with_event(
+                |delta: &i32| {
+                    let delta = *delta;
+                    set_count.mutate(move |count| *count += delta)
+                },
+                |ctx| {
+                    log("parent render");
+                    (
+                        Native::Button {
+                            label: "+1".to_string(),
+                            on_click: ctx.event(1),
+                        },
+                        Native::Button {
+                            label: "+2".to_string(),
+                            on_click: ctx.event(2),
+                        },
+                        Child { count, parity },
+                    )
+                },
+            )
+        }
+    }
+
+    #[derive(Debug)]
+    struct Child {
+        count: Signal<i32>,
+        parity: Signal<String>,
+    }
+
+    impl StaticType for Child {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Child>()
+        }
+    }
+
+    impl Component for Child {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let doubled = ctx.memo(|| {
+                log(format!("child memo {}", *self.count.get()));
+                *self.count.get() * 2
+            });
+            ctx.effect("log parity", || {
+                if self.parity.on_effect() {
+                    log("child effect");
+                }
+            });
+            ctx.render(|| {
+                log(format!("child render {}", *self.parity.get()));
+                (
+                    Native::Text {
+                        text: self.parity.clone(),
+                    },
+                    Doubled { doubled },
+                )
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct Doubled {
+        doubled: Signal<i32>,
+    }
+
+    impl StaticType for Doubled {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Doubled>()
+        }
+    }
+
+    impl Component for Doubled {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let text = ctx.memo(|| format!("Doubled {}", *self.doubled.get()));
+            ctx.render(|| {
+                log("doubled render");
+                Native::Text { text }
+            })
+        }
+    }
+
+    #[test]
+    fn children_rerun_on_parent_signals_without_the_parent_rendering() {
+        let mut renderer = TestRenderer::new(Parent);
+        assert_eq!(
+            take_log(),
+            [
+                "parent render",
+                "child memo 0",
+                "child effect",
+                "child render even",
+                "doubled render",
+            ]
+        );
+
+        renderer.click(&renderer.get_by_text("+1"));
+
+        assert_eq!(
+            take_log(),
+            [
+                "child memo 1",
+                "child effect",
+                "child render odd",
+                // Re-rendered by the child, not by a signal.
+                "doubled render",
+            ]
+        );
+        renderer.get_by_text("odd");
+        renderer.get_by_text("Doubled 2");
+    }
+
+    #[test]
+    fn children_skip_parent_signals_that_did_not_change() {
+        let mut renderer = TestRenderer::new(Parent);
+        take_log();
+
+        renderer.click(&renderer.get_by_text("+2"));
+
+        assert_eq!(take_log(), ["child memo 2"]);
+        renderer.get_by_text("even");
+        renderer.get_by_text("Doubled 4");
+    }
+
+    #[test]
+    fn unmounting_a_reader_unsubscribes_it_from_the_owner_signal() {
+        let (runtime, root_holder) = mount(Parent);
+        let parent_id = root_holder.component_instance.component_id;
+        let count_id = state_id(&root_holder, 0);
+        let readers = |runtime: &RuntimeState| {
+            let mut readers = runtime
+                .subscriptions
+                .borrow()
+                .subscribers([&count_id])
+                .collect::<Vec<_>>();
+            readers.sort();
+            readers
+        };
+        assert_eq!(readers(&runtime).len(), 2, "the parent memo and the child");
+
+        unmount_visit(&runtime, root_holder.children.borrow()[2].clone());
+
+        assert_eq!(readers(&runtime), [parent_id]);
+        take_log();
+    }
+
+    #[derive(Debug)]
+    struct Page;
+
+    impl StaticType for Page {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Page>()
+        }
+    }
+
+    impl Component for Page {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            log("page");
+            ctx.render(|| (Panel, Sidebar))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Panel;
+
+    impl StaticType for Panel {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Panel>()
+        }
+    }
+
+    impl Component for Panel {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            log("panel");
+            ctx.render(|| Clicks)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Clicks;
+
+    impl StaticType for Clicks {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Clicks>()
+        }
+    }
+
+    impl Component for Clicks {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (count, set_count) = ctx.state(|| 0);
+            ctx.render_with_event(
+                |_: &()| set_count.mutate(|count| *count += 1),
+                |ctx| {
+                    log("clicks");
+                    Native::Button {
+                        label: format!("Clicked {}", *count.get()),
+                        on_click: ctx.event(()),
+                    }
+                },
+            )
+        }
+    }
+
+    #[derive(Debug)]
+    struct Sidebar;
+
+    impl StaticType for Sidebar {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Sidebar>()
+        }
+    }
+
+    impl Component for Sidebar {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            log("sidebar");
+            let text = ctx.memo(|| "Sidebar".to_string());
+            ctx.render(|| Native::Text { text })
+        }
+    }
+
+    #[test]
+    fn update_skips_components_that_are_not_subscribed() {
+        let mut renderer = TestRenderer::new(Page);
+        assert_eq!(take_log(), ["page", "panel", "clicks", "sidebar"]);
+
+        renderer.click(&renderer.get_by_text("Clicked 0"));
+
+        // Page and Panel are only walked through to reach the reader.
+        assert_eq!(take_log(), ["clicks"]);
+        renderer.get_by_text("Clicked 1");
+        renderer.get_by_text("Sidebar");
+    }
+}
@@ -0,0 +1,296 @@
+use super::*;
+use std::{
+    cell::OnceCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Where a runtime's tasks run: the tokio runtime it was started in, or,
+/// if there was none, one of its own that is started on the first spawn.
+pub(crate) struct TaskSpawner {
+    started_in: Option<tokio::runtime::Handle>,
+    own: OnceCell<tokio::runtime::Runtime>,
+}
+
+impl TaskSpawner {
+    pub(crate) fn new() -> Self {
+        Self {
+            started_in: tokio::runtime::Handle::try_current().ok(),
+            own: OnceCell::new(),
+        }
+    }
+
+    pub(crate) fn handle(&self) -> tokio::runtime::Handle {
+        if let Some(handle) = &self.started_in {
+            return handle.clone();
+        }
+        self.own
+            .get_or_init(|| {
+                tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(1)
+                    .enable_time()
+                    .build()
+                    .expect("failed to start a tokio runtime for tasks")
+            })
+            .handle()
+            .clone()
+    }
+}
+
+impl Drop for TaskSpawner {
+    fn drop(&mut self) {
+        // Tasks are aborted on unmount already. Not waiting for them also
+        // keeps this from blocking when dropped from async code.
+        if let Some(own) = self.own.take() {
+            own.shutdown_background();
+        }
+    }
+}
+
+/// The state of a `Context::resource`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resource<T> {
+    Loading,
+    Ready(T),
+}
+
+impl<T> Resource<T> {
+    pub fn ready(&self) -> Option<&T> {
+        match self {
+            Resource::Loading => None,
+            Resource::Ready(value) => Some(value),
+        }
+    }
+}
+
+/// Spawns the future made by `task` like an effect: on the first render and
+/// whenever a signal `task` read changed. The effect's cleanup aborts the
+/// future, so a future never outlives its inputs or its component.
+pub(crate) fn handle_spawn<Task: Future<Output = ()> + Send + 'static>(
+    ctx: &Context,
+    name: &'static str,
+    task: impl FnOnce() -> Task,
+) {
+    if !ctx.check_hook(HookSlot::new::<Task>(HookKind::Task { name })) {
+        return;
+    }
+    let tasks = &ctx.runtime.tasks;
+    run_effect(ctx, || {
+        let task = tasks.handle().spawn(task());
+        move || task.abort()
+    });
+}
+
+pub(crate) fn handle_resource<
+    T: Send + Sync + Debug + 'static,
+    Load: Future<Output = T> + Send + 'static,
+>(
+    ctx: &Context,
+    name: &'static str,
+    load: impl FnOnce() -> Load,
+) -> Signal<Resource<T>> {
+    let (resource, set_resource) = ctx.state(|| Resource::Loading);
+    if !ctx.check_hook(HookSlot::new::<T>(HookKind::Task { name })) {
+        return resource;
+    }
+    let tasks = &ctx.runtime.tasks;
+    let is_ready = matches!(*resource.peek(), Resource::Ready(_));
+    run_effect(ctx, || {
+        let load = load();
+        if is_ready {
+            set_resource.clone().set(Resource::Loading);
+        }
+        // An aborted future may still be finishing on another thread, so
+        // its value is only taken if it wasn't aborted by the time the
+        // runtime gets to it.
+        let is_aborted = Arc::new(AtomicBool::new(false));
+        let task = tasks.handle().spawn({
+            let is_aborted = is_aborted.clone();
+            async move {
+                let value = load.await;
+                set_resource.mutate(move |resource| {
+                    if !is_aborted.load(Ordering::SeqCst) {
+                        *resource = Resource::Ready(value);
+                    }
+                });
+            }
+        });
+        move || {
+            is_aborted.store(true, Ordering::SeqCst);
+            task.abort();
+        }
+    });
+    resource
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// Sets its flag when the future holding it is dropped, which is what
+    /// aborting a task does.
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn wait_until(flag: &AtomicBool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !flag.load(Ordering::SeqCst) {
+            assert!(Instant::now() < deadline, "timed out");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[derive(Debug)]
+    struct Profile {
+        /// Each load waits for a permit, so the test decides when it is done.
+        loads: Arc<tokio::sync::Semaphore>,
+        is_dropped: Arc<AtomicBool>,
+        /// The name of the thread the last load finished on.
+        loaded_on: Arc<Mutex<Option<String>>>,
+    }
+
+    impl StaticType for Profile {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Profile>()
+        }
+    }
+
+    impl Component for Profile {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (user_id, set_user_id) = ctx.state(|| 1);
+            let name = ctx.resource("load name", || {
+                let user_id = *user_id.get();
+                let loads = self.loads.clone();
+                let loaded_on = self.loaded_on.clone();
+                async move {
+                    loads.acquire().await.unwrap().forget();
+                    *loaded_on.lock().unwrap() = std::thread::current().name().map(String::from);
+                    format!("User {}", user_id)
+                }
+            });
+            ctx.spawn("wait forever", || {
+                let _ = *user_id.get();
+                let flag = DropFlag(self.is_dropped.clone());
+                async move {
+                    let _flag = flag;
+                    std::future::pending::<()>().await
+                }
+            });
+            let text = ctx.memo(|| match &*name.get() {
+                Resource::Loading => "Loading".to_string(),
+                Resource::Ready(name) => name.clone(),
+            });
+            ctx.render_with_event(
+                |_: &()| set_user_id.mutate(|user_id| *user_id += 1),
+                |ctx| {
+                    Native::view((
+                        Native::Text { text },
+                        Native::Button {
+                            label: "Next".to_string(),
+                            on_click: ctx.event(()),
+                        },
+                    ))
+                },
+            )
+        }
+    }
+
+    struct Rendered {
+        renderer: TestRenderer,
+        loads: Arc<tokio::sync::Semaphore>,
+        is_dropped: Arc<AtomicBool>,
+        loaded_on: Arc<Mutex<Option<String>>>,
+    }
+
+    fn render_profile() -> Rendered {
+        let loads = Arc::new(tokio::sync::Semaphore::new(0));
+        let is_dropped = Arc::new(AtomicBool::new(false));
+        let loaded_on = Arc::new(Mutex::new(None));
+        let renderer = TestRenderer::new(Profile {
+            loads: loads.clone(),
+            is_dropped: is_dropped.clone(),
+            loaded_on: loaded_on.clone(),
+        });
+        Rendered {
+            renderer,
+            loads,
+            is_dropped,
+            loaded_on,
+        }
+    }
+
+    #[test]
+    fn resource_is_loading_until_the_future_is_done() {
+        let Rendered {
+            mut renderer,
+            loads,
+            ..
+        } = render_profile();
+        renderer.get_by_text("Loading");
+
+        loads.add_permits(1);
+        renderer.wait_for_update();
+        renderer.get_by_text("User 1");
+
+        renderer.click(&renderer.get_by_text("Next"));
+        renderer.get_by_text("Loading");
+        loads.add_permits(1);
+        renderer.wait_for_update();
+        renderer.get_by_text("User 2");
+    }
+
+    #[test]
+    fn changed_input_aborts_the_running_task() {
+        let Rendered {
+            mut renderer,
+            is_dropped,
+            ..
+        } = render_profile();
+
+        renderer.click(&renderer.get_by_text("Next"));
+
+        wait_until(&is_dropped);
+    }
+
+    #[test]
+    fn unmount_aborts_the_running_task() {
+        let Rendered {
+            renderer,
+            is_dropped,
+            ..
+        } = render_profile();
+        assert!(!is_dropped.load(Ordering::SeqCst));
+
+        drop(renderer);
+
+        wait_until(&is_dropped);
+    }
+
+    #[test]
+    fn tasks_run_on_the_tokio_runtime_they_were_started_in() {
+        let tokio = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("caller runtime")
+            .build()
+            .unwrap();
+        let _guard = tokio.enter();
+        let Rendered {
+            mut renderer,
+            loads,
+            loaded_on,
+            ..
+        } = render_profile();
+
+        loads.add_permits(1);
+        renderer.wait_for_update();
+
+        renderer.get_by_text("User 1");
+        assert_eq!(loaded_on.lock().unwrap().as_deref(), Some("caller runtime"));
+    }
+}
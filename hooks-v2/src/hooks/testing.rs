@@ -0,0 +1,363 @@
+use super::*;
+
+/// A native node as the test renderer has it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestNode {
+    pub id: usize,
+    pub kind: NativeKind,
+    pub props: Vec<Prop>,
+    pub children: Vec<usize>,
+}
+
+impl TestNode {
+    pub fn prop(&self, name: &str) -> Option<&Prop> {
+        self.props.iter().find(|prop| prop.name() == name)
+    }
+
+    /// The text a `Text` shows or the label of a `Button`.
+    pub fn text(&self) -> Option<&str> {
+        self.props.iter().find_map(|prop| match prop {
+            Prop::Text(text) | Prop::Label(text) => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn test_id(&self) -> Option<&str> {
+        self.props.iter().find_map(|prop| match prop {
+            Prop::TestId(test_id) => Some(test_id.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// A backend that keeps the native tree in memory.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    nodes: HashMap<usize, TestNode>,
+    parents: HashMap<usize, Option<usize>>,
+    root_children: Vec<usize>,
+}
+
+impl MemoryBackend {
+    pub fn node(&self, node_id: usize) -> Option<&TestNode> {
+        self.nodes.get(&node_id)
+    }
+
+    /// Every attached node, parents before their children and siblings in
+    /// order.
+    pub fn nodes(&self) -> Vec<&TestNode> {
+        let mut nodes = vec![];
+        let mut stack = self.root_children.iter().rev().collect::<Vec<_>>();
+        while let Some(node_id) = stack.pop() {
+            let node = &self.nodes[node_id];
+            nodes.push(node);
+            stack.extend(node.children.iter().rev());
+        }
+        nodes
+    }
+
+    pub fn root_children(&self) -> &[usize] {
+        &self.root_children
+    }
+
+    fn children_mut(&mut self, parent_id: Option<usize>) -> &mut Vec<usize> {
+        match parent_id {
+            Some(parent_id) => &mut self.nodes.get_mut(&parent_id).unwrap().children,
+            None => &mut self.root_children,
+        }
+    }
+
+    fn detach(&mut self, node_id: usize) {
+        if let Some(parent_id) = self.parents.remove(&node_id) {
+            self.children_mut(parent_id)
+                .retain(|child| *child != node_id);
+        }
+    }
+}
+
+impl NativeBackend for MemoryBackend {
+    fn apply(&mut self, patch: Patch) {
+        match patch {
+            Patch::CreateNode { node_id, kind } => {
+                self.nodes.insert(
+                    node_id,
+                    TestNode {
+                        id: node_id,
+                        kind,
+                        props: vec![],
+                        children: vec![],
+                    },
+                );
+            }
+            Patch::SetProp { node_id, prop } => {
+                let props = &mut self.nodes.get_mut(&node_id).unwrap().props;
+                match props.iter_mut().find(|prev| prev.is_same_prop(&prop)) {
+                    Some(prev) => *prev = prop,
+                    None => props.push(prop),
+                }
+            }
+            Patch::InsertChild {
+                parent_id,
+                node_id,
+                index,
+            } => {
+                self.detach(node_id);
+                self.children_mut(parent_id).insert(index, node_id);
+                self.parents.insert(node_id, parent_id);
+            }
+            Patch::RemoveNode { node_id } => {
+                self.detach(node_id);
+                self.nodes.remove(&node_id);
+            }
+            Patch::Commit => {}
+        }
+    }
+}
+
+/// Mounts a component without a window and lets tests look at what it
+/// renders and interact with it the way a user would.
+///
+/// ```ignore
+/// let mut renderer = TestRenderer::new(Counter);
+/// renderer.click(&renderer.get_by_text("+1"));
+/// assert_eq!(renderer.get_by_test_id("count").text(), Some("1"));
+/// ```
+///
+/// Updates are only processed by `run_until_idle`, which the event helpers
+/// call after firing. Dropping the renderer shuts its runtime down.
+pub struct TestRenderer {
+    runtime: Runtime<MemoryBackend>,
+}
+
+impl TestRenderer {
+    pub fn new(component: impl Component + 'static) -> Self {
+        Self::from_runtime(start(component, MemoryBackend::default()))
+    }
+
+    /// Times the timers with `clock`, usually a `VirtualClock` the test
+    /// keeps a clone of to advance it.
+    pub fn with_clock(component: impl Component + 'static, clock: impl Clock + 'static) -> Self {
+        Self::from_runtime(start_with_clock(component, MemoryBackend::default(), clock))
+    }
+
+    fn from_runtime(runtime: Runtime<MemoryBackend>) -> Self {
+        let mut renderer = Self { runtime };
+        renderer.run_until_idle();
+        renderer
+    }
+
+    pub fn runtime(&self) -> &Runtime<MemoryBackend> {
+        &self.runtime
+    }
+
+    pub fn backend(&self) -> &MemoryBackend {
+        self.runtime.backend()
+    }
+
+    /// Processes queued updates until there are none left, so the tree is
+    /// settled.
+    pub fn run_until_idle(&mut self) {
+        self.runtime.run_until_idle();
+    }
+
+    /// Waits until something is sent to the runtime, like the result of a
+    /// task, and runs until idle.
+    pub fn wait_for_update(&mut self) {
+        self.runtime.tick();
+        self.run_until_idle();
+    }
+
+    pub fn find_all(&self, predicate: impl Fn(&TestNode) -> bool) -> Vec<TestNode> {
+        self.backend()
+            .nodes()
+            .into_iter()
+            .filter(|node| predicate(node))
+            .cloned()
+            .collect()
+    }
+
+    pub fn find_all_by_kind(&self, kind: NativeKind) -> Vec<TestNode> {
+        self.find_all(|node| node.kind == kind)
+    }
+
+    /// The one node showing exactly `text`. Panics if there is none or more
+    /// than one.
+    pub fn get_by_text(&self, text: &str) -> TestNode {
+        self.get_one(&format!("text {:?}", text), |node| {
+            node.text() == Some(text)
+        })
+    }
+
+    /// The one node with the test id. Panics if there is none or more than
+    /// one.
+    pub fn get_by_test_id(&self, test_id: &str) -> TestNode {
+        self.get_one(&format!("test id {:?}", test_id), |node| {
+            node.test_id() == Some(test_id)
+        })
+    }
+
+    fn get_one(&self, description: &str, predicate: impl Fn(&TestNode) -> bool) -> TestNode {
+        let mut nodes = self.find_all(predicate);
+        match nodes.len() {
+            1 => nodes.remove(0),
+            0 => panic!("no node with {}", description),
+            count => panic!("{} nodes with {}", count, description),
+        }
+    }
+
+    pub fn click(&mut self, node: &TestNode) {
+        self.fire(node, "on_click");
+    }
+
+    /// Toggles a checkbox to the opposite of what it shows.
+    pub fn toggle(&mut self, node: &TestNode) {
+        let checked = !matches!(
+            self.current(node).prop("checked"),
+            Some(Prop::Checked(true))
+        );
+        self.fire_with(node, "on_toggle", Toggled { checked });
+    }
+
+    /// Types into a text input, replacing its text with `value`.
+    pub fn change(&mut self, node: &TestNode, value: impl Into<String>) {
+        let value = value.into();
+        self.fire_with(node, "on_change", InputChanged { value });
+    }
+
+    /// Fires the event `name` on the node without a payload and runs until
+    /// idle. The event propagates like a real one, see `NativeEvent`.
+    pub fn fire(&mut self, node: &TestNode, name: &'static str) {
+        self.fire_with(node, name, ());
+    }
+
+    /// Like `fire`, with a payload.
+    pub fn fire_with<Payload: Any + Send>(
+        &mut self,
+        node: &TestNode,
+        name: &'static str,
+        payload: Payload,
+    ) {
+        let node_id = self.current(node).id;
+        self.runtime
+            .handle()
+            .fire_native_event(node_id, name, payload);
+        self.run_until_idle();
+    }
+
+    fn current(&self, node: &TestNode) -> &TestNode {
+        self.backend()
+            .node(node.id)
+            .unwrap_or_else(|| panic!("node {} is not mounted", node.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Settings;
+
+    enum Event {
+        SetDarkMode(bool),
+        Rename(String),
+    }
+
+    impl StaticType for Settings {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Settings>()
+        }
+    }
+
+    impl Component for Settings {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (dark_mode, set_dark_mode) = ctx.state(|| false);
+            let (name, set_name) = ctx.state(String::new);
+            let label = ctx.memo(|| if *dark_mode.get() { "Dark" } else { "Light" }.to_string());
+            ctx.render_with_event(
+                |event| match event {
+                    Event::SetDarkMode(dark_mode) => set_dark_mode.set(*dark_mode),
+                    Event::Rename(name) => set_name.set(name.clone()),
+                },
+                |ctx| {
+                    Native::view((
+                        Native::Text { text: label },
+                        test_id(
+                            "dark-mode",
+                            Native::Checkbox {
+                                checked: dark_mode,
+                                on_toggle: ctx.event_with(|toggled: Toggled| {
+                                    Event::SetDarkMode(toggled.checked)
+                                }),
+                            },
+                        ),
+                        test_id(
+                            "name",
+                            Native::TextInput {
+                                value: name,
+                                on_change: ctx.event_with(|changed: InputChanged| {
+                                    Event::Rename(changed.value)
+                                }),
+                            },
+                        ),
+                    ))
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn finds_nodes_and_settles_after_events() {
+        let mut renderer = TestRenderer::new(Settings);
+        let [view] = &renderer.find_all_by_kind(NativeKind::View)[..] else {
+            panic!("expected one view");
+        };
+        assert_eq!(view.children.len(), 3);
+        renderer.get_by_text("Light");
+
+        renderer.toggle(&renderer.get_by_test_id("dark-mode"));
+
+        renderer.get_by_text("Dark");
+        assert_eq!(
+            renderer.get_by_test_id("dark-mode").prop("checked"),
+            Some(&Prop::Checked(true))
+        );
+
+        renderer.toggle(&renderer.get_by_test_id("dark-mode"));
+        renderer.get_by_text("Light");
+    }
+
+    #[test]
+    fn change_passes_the_text_to_the_handler() {
+        let mut renderer = TestRenderer::new(Settings);
+
+        renderer.change(&renderer.get_by_test_id("name"), "Ruact");
+
+        assert_eq!(
+            renderer.get_by_test_id("name").prop("value"),
+            Some(&Prop::Value("Ruact".to_string()))
+        );
+    }
+
+    #[test]
+    fn payload_of_another_type_is_reported() {
+        let mut renderer = TestRenderer::new(Settings);
+        renderer.runtime().set_error_reporter(collect_error);
+
+        renderer.fire(&renderer.get_by_test_id("name"), "on_change");
+
+        assert_eq!(
+            take_reported(),
+            [RuactError::EventPayloadMismatch {
+                component_type_name: std::any::type_name::<Settings>(),
+                expected_type_name: std::any::type_name::<InputChanged>(),
+            }]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no node with text \"Dim\"")]
+    fn get_by_text_panics_without_a_match() {
+        TestRenderer::new(Settings).get_by_text("Dim");
+    }
+}
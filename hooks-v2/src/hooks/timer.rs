@@ -0,0 +1,311 @@
+use super::*;
+use std::{sync::Mutex, time::Duration};
+
+/// Undoes `Clock::set_timer`. Called at most once; a timeout that already
+/// fired may be cancelled too.
+pub type CancelTimer = Box<dyn FnOnce() + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timer {
+    /// Fires once, after the duration.
+    Timeout(Duration),
+    /// Fires every time the duration passes. Never zero; `Context::interval`
+    /// reports a zero period instead of setting the timer.
+    Interval(Duration),
+}
+
+/// What timers are measured against. The runtime uses the system time, see
+/// `start`; tests can pass a `VirtualClock` to `start_with_clock` and move
+/// time forward themselves.
+pub trait Clock {
+    /// Calls `fire` as `timer` says until the timer is cancelled. `fire` may
+    /// be called from any thread.
+    fn set_timer(&self, timer: Timer, fire: Box<dyn Fn() + Send + Sync>) -> CancelTimer;
+}
+
+/// System time, ticking on the runtime's tokio runtime.
+pub(crate) struct SystemClock {
+    pub(crate) tasks: Rc<TaskSpawner>,
+}
+
+impl Clock for SystemClock {
+    fn set_timer(&self, timer: Timer, fire: Box<dyn Fn() + Send + Sync>) -> CancelTimer {
+        let task = self.tasks.handle().spawn(async move {
+            match timer {
+                Timer::Timeout(delay) => {
+                    tokio::time::sleep(delay).await;
+                    fire();
+                }
+                Timer::Interval(period) => {
+                    let start = tokio::time::Instant::now() + period;
+                    let mut interval = tokio::time::interval_at(start, period);
+                    loop {
+                        interval.tick().await;
+                        fire();
+                    }
+                }
+            }
+        });
+        Box::new(move || task.abort())
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can keep one and hand another to the runtime.
+///
+/// ```ignore
+/// let clock = VirtualClock::new();
+/// let mut renderer = TestRenderer::with_clock(Stopwatch, clock.clone());
+/// clock.advance(Duration::from_secs(1));
+/// renderer.run_until_idle();
+/// ```
+#[derive(Clone, Default)]
+pub struct VirtualClock {
+    timers: Arc<Mutex<VirtualTimers>>,
+}
+
+#[derive(Default)]
+struct VirtualTimers {
+    now: Duration,
+    next_timer_id: usize,
+    timers: Vec<VirtualTimer>,
+}
+
+struct VirtualTimer {
+    id: usize,
+    due: Duration,
+    period: Option<Duration>,
+    fire: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How far the clock was advanced since it was made.
+    pub fn now(&self) -> Duration {
+        self.timers.lock().unwrap().now
+    }
+
+    /// How many timers are set and not cancelled, intervals included.
+    pub fn pending_timers(&self) -> usize {
+        self.timers.lock().unwrap().timers.len()
+    }
+
+    /// Moves the time forward, firing every timer that comes due on the way
+    /// in the order it comes due. An interval fires once for every period
+    /// that passed.
+    pub fn advance(&self, by: Duration) {
+        let until = self.now() + by;
+        loop {
+            let fire = {
+                let mut timers = self.timers.lock().unwrap();
+                let Some(index) = (0..timers.timers.len())
+                    .filter(|&index| timers.timers[index].due <= until)
+                    .min_by_key(|&index| (timers.timers[index].due, timers.timers[index].id))
+                else {
+                    break;
+                };
+                let timer = &mut timers.timers[index];
+                let due = timer.due;
+                let fire = timer.fire.clone();
+                match timer.period {
+                    Some(period) => timer.due += period,
+                    None => {
+                        timers.timers.remove(index);
+                    }
+                }
+                timers.now = due;
+                fire
+            };
+            // Outside the lock, so the timer can be cancelled from `fire`.
+            fire();
+        }
+        self.timers.lock().unwrap().now = until;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn set_timer(&self, timer: Timer, fire: Box<dyn Fn() + Send + Sync>) -> CancelTimer {
+        let mut timers = self.timers.lock().unwrap();
+        let id = timers.next_timer_id;
+        timers.next_timer_id += 1;
+        let (delay, period) = match timer {
+            Timer::Timeout(delay) => (delay, None),
+            Timer::Interval(period) => (period, Some(period)),
+        };
+        let due = timers.now + delay;
+        timers.timers.push(VirtualTimer {
+            id,
+            due,
+            period,
+            fire: Arc::from(fire),
+        });
+
+        let timers = Arc::downgrade(&self.timers);
+        Box::new(move || {
+            if let Some(timers) = timers.upgrade() {
+                timers.lock().unwrap().timers.retain(|timer| timer.id != id);
+            }
+        })
+    }
+}
+
+/// Sends `event` to the component as `timer` says, like an effect that sets
+/// the timer on the first render and cancels it on unmount. The effect reads
+/// no signals, so the `timer` and `event` of later renders are dropped.
+pub(crate) fn handle_timer<Event: Send + Sync + 'static>(
+    ctx: &Context,
+    timer: Timer,
+    event: Event,
+) {
+    if !ctx.check_hook(HookSlot::new::<Event>(HookKind::Timer)) {
+        return;
+    }
+    let runtime = ctx.runtime.clone();
+    let component_type_name = ctx.instance.component_type_name;
+    let event_callback = EventCallback {
+        tx: ctx.runtime.tx.clone(),
+        component_id: ctx.instance.component_id,
+        source: EventSource::Event(Arc::new(event)),
+    };
+    run_effect(ctx, move || -> CancelTimer {
+        if timer == Timer::Interval(Duration::ZERO) {
+            runtime.report_error(RuactError::ZeroInterval {
+                component_type_name,
+            });
+            return Box::new(|| {});
+        }
+        runtime
+            .clock
+            .set_timer(timer, Box::new(move || event_callback.call()))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Stopwatch;
+
+    enum Event {
+        Tick,
+        Alarm,
+    }
+
+    impl StaticType for Stopwatch {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Stopwatch>()
+        }
+    }
+
+    impl Component for Stopwatch {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (seconds, set_seconds) = ctx.state(|| 0);
+            let (alarms, set_alarms) = ctx.state(|| 0);
+            ctx.interval(Duration::from_secs(1), Event::Tick);
+            ctx.timeout(Duration::from_secs(3), Event::Alarm);
+            let text = ctx.memo(|| format!("{}s, {} alarms", *seconds.get(), *alarms.get()));
+            ctx.render_with_event(
+                |event| match event {
+                    Event::Tick => set_seconds.mutate(|seconds| *seconds += 1),
+                    Event::Alarm => set_alarms.mutate(|alarms| *alarms += 1),
+                },
+                |_| Native::Text { text },
+            )
+        }
+    }
+
+    #[test]
+    fn timers_fire_as_the_virtual_clock_advances() {
+        let clock = VirtualClock::new();
+        let mut renderer = TestRenderer::with_clock(Stopwatch, clock.clone());
+        let mut advance = |millis| {
+            clock.advance(Duration::from_millis(millis));
+            renderer.run_until_idle();
+            renderer.find_all_by_kind(NativeKind::Text)[0]
+                .text()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(advance(999), "0s, 0 alarms");
+        assert_eq!(advance(1), "1s, 0 alarms");
+        assert_eq!(advance(2000), "3s, 1 alarms");
+        assert_eq!(advance(10_000), "13s, 1 alarms");
+    }
+
+    #[test]
+    fn unmount_cancels_the_timers() {
+        let clock = VirtualClock::new();
+        let renderer = TestRenderer::with_clock(Stopwatch, clock.clone());
+        assert_eq!(clock.pending_timers(), 2);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.pending_timers(), 1);
+
+        drop(renderer);
+
+        assert_eq!(clock.pending_timers(), 0);
+    }
+
+    #[test]
+    fn system_clock_fires_in_real_time() {
+        #[derive(Debug)]
+        struct Fast;
+
+        impl StaticType for Fast {
+            fn static_type_id(&self) -> TypeId {
+                TypeId::of::<Fast>()
+            }
+        }
+
+        impl Component for Fast {
+            fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+                let (ticks, set_ticks) = ctx.state(|| 0);
+                ctx.timeout(Duration::from_millis(1), ());
+                let text = ctx.memo(|| ticks.get().to_string());
+                ctx.render_with_event(
+                    |_: &()| set_ticks.mutate(|ticks| *ticks += 1),
+                    |_| Native::Text { text },
+                )
+            }
+        }
+
+        let mut renderer = TestRenderer::new(Fast);
+        renderer.get_by_text("0");
+
+        renderer.wait_for_update();
+
+        renderer.get_by_text("1");
+    }
+
+    #[derive(Debug)]
+    struct Spinner;
+
+    impl StaticType for Spinner {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Spinner>()
+        }
+    }
+
+    impl Component for Spinner {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            ctx.interval(Duration::ZERO, ());
+            ctx.render_with_event(|_: &()| log("tick"), |_| ())
+        }
+    }
+
+    #[test]
+    fn zero_interval_is_reported_instead_of_set() {
+        let (_runtime, _root_holder) = mount(Spinner);
+
+        assert_eq!(
+            take_reported(),
+            [RuactError::ZeroInterval {
+                component_type_name: std::any::type_name::<Spinner>(),
+            }]
+        );
+    }
+}
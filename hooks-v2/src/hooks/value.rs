@@ -1,9 +1,40 @@
-use std::{any::Any, fmt::Debug};
+//! Storage for hook values.
+//!
+//! # Aliasing model
+//!
+//! Every state and memo value lives in its own `Rc<RefCell<T>>`, shared by
+//! its slot in the owning instance's `SlotArena` and by every `Signal`
+//! handed out for it. There is no unsafe code on either side:
+//!
+//! - Signals read through `Signal::get`, which returns the cell's `Ref`
+//!   guard, so a reference out of a signal can't outlive its guard, however
+//!   long the signal itself lives.
+//! - Values are only written by the runtime, through `write_value`: state
+//!   items are applied between renders in `run_batch`, and a memo is
+//!   recomputed by its own hook call. A guard still alive at that point,
+//!   one a component leaked or kept in a long-lived closure, makes the
+//!   write panic instead of aliasing.
+//!
+//! All other per-instance bookkeeping is behind a plain `RefCell` or `Cell`
+//! and is never borrowed across a call into user code.
+//!
+//! The tests below drive every path that reads or writes a value. CI runs
+//! them under Miri with `cargo +nightly miri test --lib hooks::value`.
+
+use super::*;
+use std::{
+    any::Any,
+    cell::{RefCell, RefMut},
+    fmt::Debug,
+    rc::Rc,
+};
 
 pub trait Value: Send + Sync {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
     fn as_any(&self) -> &dyn std::any::Any;
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
+    fn into_any(self: Box<Self>) -> Box<dyn std::any::Any>;
+    fn type_name(&self) -> &'static str;
 }
 
 impl<T: Debug + Send + Sync + Any> Value for T {
@@ -16,6 +47,12 @@ impl<T: Debug + Send + Sync + Any> Value for T {
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
+    fn into_any(self: Box<Self>) -> Box<dyn std::any::Any> {
+        self
+    }
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
 }
 
 impl Debug for dyn Value {
@@ -23,3 +60,199 @@ impl Debug for dyn Value {
         Value::fmt(self, f)
     }
 }
+
+/// A type-erased `Rc<RefCell<T>>` holding a hook value.
+pub(crate) trait ValueCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
+    fn into_any(self: Rc<Self>) -> Rc<dyn Any>;
+    fn type_name(&self) -> &'static str;
+    /// Fails with the type name of `value` if it is of another type.
+    fn set(&self, value: Box<dyn Value>) -> Result<(), &'static str>;
+    /// Returns whether `mutate` changed the value. Fails with the type name
+    /// `mutate` expected if it is another type.
+    fn mutate(&self, mutate: MutateFn) -> Result<bool, &'static str>;
+}
+
+impl<T: Value + Any> ValueCell for RefCell<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.try_borrow() {
+            Ok(value) => Value::fmt(&*value, f),
+            Err(_) => write!(f, "<being written>"),
+        }
+    }
+    fn into_any(self: Rc<Self>) -> Rc<dyn Any> {
+        self
+    }
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+    fn set(&self, value: Box<dyn Value>) -> Result<(), &'static str> {
+        let type_name = Value::type_name(value.as_ref());
+        let value = value.into_any().downcast::<T>().map_err(|_| type_name)?;
+        *write_value(self) = *value;
+        Ok(())
+    }
+    fn mutate(&self, mutate: MutateFn) -> Result<bool, &'static str> {
+        mutate(&mut *write_value(self))
+    }
+}
+
+impl Debug for dyn ValueCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        ValueCell::fmt(self, f)
+    }
+}
+
+/// Borrows a value for the runtime to write. Panics if a guard from
+/// `Signal::get` is still alive, see the aliasing model above.
+pub(crate) fn write_value<T>(cell: &RefCell<T>) -> RefMut<'_, T> {
+    cell.try_borrow_mut()
+        .expect("hook value written while a `Signal::get` guard of it is alive")
+}
+
+pub(crate) fn downcast_value_cell<T: Any>(cell: &Rc<dyn ValueCell>) -> Rc<RefCell<T>> {
+    cell.clone().into_any().downcast::<RefCell<T>>().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct TextParent;
+
+    impl StaticType for TextParent {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<TextParent>()
+        }
+    }
+
+    impl Component for TextParent {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (text, _) = ctx.state(|| "a".to_string());
+            ctx.render(|| TextChild { text })
+        }
+    }
+
+    #[derive(Debug)]
+    struct TextChild {
+        text: Signal<String>,
+    }
+
+    impl StaticType for TextChild {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<TextChild>()
+        }
+    }
+
+    impl Component for TextChild {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            ctx.render(|| log(self.text.get().clone()))
+        }
+    }
+
+    #[test]
+    fn child_reads_state_written_while_it_holds_the_signal() {
+        let (runtime, root_holder) = mount(TextParent);
+
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![
+                set_state(&root_holder, 0, "b".to_string()),
+                mutate_state(&root_holder, 0, |text: &mut String| text.push('c')),
+            ],
+        );
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![mutate_state(&root_holder, 0, |text: &mut String| {
+                text.clear()
+            })],
+        );
+
+        assert_eq!(take_log(), ["a", "bc", ""]);
+    }
+
+    #[derive(Debug)]
+    struct MemoParent;
+
+    impl StaticType for MemoParent {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<MemoParent>()
+        }
+    }
+
+    impl Component for MemoParent {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (count, _) = ctx.state(|| 1);
+            let doubled = ctx.memo(|| *count.get() * 2);
+            ctx.render(|| MemoChild { doubled })
+        }
+    }
+
+    #[derive(Debug)]
+    struct MemoChild {
+        doubled: Signal<i32>,
+    }
+
+    impl StaticType for MemoChild {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<MemoChild>()
+        }
+    }
+
+    impl Component for MemoChild {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let tripled = ctx.memo(|| *self.doubled.get() / 2 * 3);
+            ctx.render(|| log(format!("{} {}", *self.doubled.get(), *tripled.get())))
+        }
+    }
+
+    #[test]
+    fn child_reads_parent_memo_after_it_recomputes() {
+        let (runtime, root_holder) = mount(MemoParent);
+
+        run_batch(&runtime, &root_holder, vec![set_state(&root_holder, 0, 3)]);
+        run_batch(
+            &runtime,
+            &root_holder,
+            vec![mutate_state(&root_holder, 0, |count: &mut i32| *count += 1)],
+        );
+
+        assert_eq!(take_log(), ["2 3", "6 9", "8 12"]);
+    }
+
+    thread_local! {
+        static KEPT: RefCell<Option<Signal<i32>>> = const { RefCell::new(None) };
+    }
+
+    /// Hands its state out of the render, the way a component could keep it
+    /// in a long-lived closure.
+    #[derive(Debug)]
+    struct Keeper;
+
+    impl StaticType for Keeper {
+        fn static_type_id(&self) -> TypeId {
+            TypeId::of::<Keeper>()
+        }
+    }
+
+    impl Component for Keeper {
+        fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+            let (count, _) = ctx.state(|| 1);
+            KEPT.with(|kept| *kept.borrow_mut() = Some(count));
+            ctx.render(|| ())
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "written while a `Signal::get` guard of it is alive")]
+    fn write_under_a_kept_guard_panics_instead_of_aliasing() {
+        let (runtime, root_holder) = mount(Keeper);
+        let count = KEPT.with(|kept| kept.take()).unwrap();
+        let _guard = count.get();
+
+        run_batch(&runtime, &root_holder, vec![set_state(&root_holder, 0, 42)]);
+    }
+}
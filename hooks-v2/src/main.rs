@@ -1,20 +1,20 @@
-mod hooks;
-
-use hooks::*;
-use std::{any::TypeId, fmt::Debug};
+use rust_test::hooks::*;
+use std::{any::TypeId, collections::BTreeSet, fmt::Debug};
 
 #[derive(Debug)]
 struct MyComponent {}
 
 enum Event {
     OnClick,
+    OnReset,
 }
 
 impl Component for MyComponent {
     fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
         let (count, set_count) = ctx.state(|| 0);
-        let fibo = ctx.memo(|| get_fibo(*count));
-        let text = ctx.memo(|| format!("Count: {}, Fibo: {}", *count, *fibo));
+        let fibo = ctx.memo(|| get_fibo(*count.get()));
+        let text = ctx.memo(|| format!("Count: {}, Fibo: {}", *count.get(), *fibo.get()));
+        let reset_text = ctx.memo(|| "Reset".to_string());
 
         ctx.render_with_event(
             |event| match event {
@@ -22,10 +22,19 @@ impl Component for MyComponent {
                     println!("Clicked");
                     set_count.mutate(|count| *count += 1)
                 }
+                Event::OnReset => set_count.set(0),
             },
-            |ctx| Button {
-                text,
-                on_click: ctx.event(Event::OnClick),
+            |ctx| {
+                (
+                    Button {
+                        text,
+                        on_click: ctx.event(Event::OnClick),
+                    },
+                    Button {
+                        text: reset_text,
+                        on_click: ctx.event(Event::OnReset),
+                    },
+                )
             },
         )
     }
@@ -37,6 +46,7 @@ impl StaticType for MyComponent {
     }
 }
 
+#[allow(dead_code)]
 mod without_event {
     use super::*;
 
@@ -51,11 +61,11 @@ mod without_event {
 
     impl Component for MyComponent {
         fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
-            let (count, set_count) = ctx.state(|| 0);
+            let (count, _set_count) = ctx.state(|| 0);
 
-            let fibo = ctx.memo(|| get_fibo(*count));
+            let fibo = ctx.memo(|| get_fibo(*count.get()));
 
-            let text = ctx.memo(|| format!("Count: {}, Fibo: {}", *count, *fibo));
+            let text = ctx.memo(|| format!("Count: {}, Fibo: {}", *count.get(), *fibo.get()));
 
             ctx.render(|| Button {
                 text,
@@ -82,18 +92,18 @@ fn get_fibo(x: u32) -> u32 {
 }
 
 #[derive(Debug)]
-struct Button<'a> {
-    text: Signal<'a, String>,
+struct Button {
+    text: Signal<String>,
     on_click: EventCallback,
 }
 
-impl StaticType for Button<'_> {
+impl StaticType for Button {
     fn static_type_id(&self) -> TypeId {
-        TypeId::of::<Button<'static>>()
+        TypeId::of::<Button>()
     }
 }
 
-impl Component for Button<'_> {
+impl Component for Button {
     fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
         ctx.effect("Print text on text effect", || {
             if self.text.on_effect() {
@@ -103,15 +113,77 @@ impl Component for Button<'_> {
 
         ctx.effect("On button render", || {
             println!("Button rendered");
+            || println!("Button unmounted")
         });
 
         ctx.render(|| Native::Button {
+            label: self.text.get().clone(),
             on_click: self.on_click.clone(),
         })
     }
 }
 
+/// Prints every patch, and clicks each button five times once the first
+/// render is committed.
+#[derive(Default)]
+struct DemoBackend {
+    handle: Option<RuntimeHandle>,
+    buttons: BTreeSet<usize>,
+    is_committed: bool,
+}
+
+impl NativeBackend for DemoBackend {
+    fn connect(&mut self, handle: RuntimeHandle) {
+        self.handle = Some(handle);
+    }
+
+    fn apply(&mut self, patch: Patch) {
+        println!("Patch: {:?}", patch);
+        match patch {
+            Patch::CreateNode {
+                node_id,
+                kind: NativeKind::Button,
+            } => {
+                self.buttons.insert(node_id);
+            }
+            Patch::RemoveNode { node_id } => {
+                self.buttons.remove(&node_id);
+            }
+            Patch::Commit if !self.is_committed => {
+                self.is_committed = true;
+                let handle = self.handle.as_ref().unwrap();
+                for &node_id in &self.buttons {
+                    for _ in 0..5 {
+                        handle.fire_native_event(node_id, "on_click", ());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 fn main() {
     let root = MyComponent {};
-    start(root);
+    let mut runtime = start(root, DemoBackend::default());
+    runtime.run_until_idle();
+    runtime.shutdown();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn click_counts_up_and_reset_clears() {
+        let mut renderer = TestRenderer::new(MyComponent {});
+
+        renderer.click(&renderer.get_by_text("Count: 0, Fibo: 0"));
+        renderer.click(&renderer.get_by_text("Count: 1, Fibo: 1"));
+        renderer.click(&renderer.get_by_text("Count: 2, Fibo: 1"));
+        renderer.get_by_text("Count: 3, Fibo: 2");
+
+        renderer.click(&renderer.get_by_text("Reset"));
+        renderer.get_by_text("Count: 0, Fibo: 0");
+    }
 }
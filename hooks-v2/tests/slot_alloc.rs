@@ -0,0 +1,132 @@
+//! Renders a component 10,000 times and checks that hook storage neither
+//! leaks nor grows, and that its hooks don't allocate once mounted. This has
+//! its own test binary because it replaces the global allocator.
+
+use rust_test::hooks::*;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    any::TypeId,
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts, per thread, the blocks allocated and not freed yet and how many
+/// times memory was requested, so the test sees its own allocations only.
+struct CountingAllocator;
+
+thread_local! {
+    static LIVE_BLOCKS: Cell<isize> = const { Cell::new(0) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    /// Allocations made while `Counter` called its hooks.
+    static HOOK_ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_block(delta: isize) {
+    let _ = LIVE_BLOCKS.try_with(|live| live.set(live.get() + delta));
+}
+
+fn count_allocation() {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_block(1);
+        count_allocation();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        count_block(-1);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+static LIVE_VALUES: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts its live instances, so a value kept alive by a leaked reference
+/// is still counted once the runtime is gone.
+#[derive(Debug, PartialEq)]
+struct Tracked(i32);
+
+impl Tracked {
+    fn new(value: i32) -> Self {
+        LIVE_VALUES.fetch_add(1, Ordering::SeqCst);
+        Self(value)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        LIVE_VALUES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug)]
+struct Counter;
+
+impl StaticType for Counter {
+    fn static_type_id(&self) -> TypeId {
+        TypeId::of::<Counter>()
+    }
+}
+
+impl Component for Counter {
+    fn component<'a>(&'a self, ctx: &'a Context) -> ContextDone {
+        let before = allocations();
+        let (count, set_count) = ctx.state(|| Tracked::new(0));
+        let doubled = ctx.memo(|| Tracked::new(count.get().0 * 2));
+        let is_even = ctx.memo(|| doubled.get().0 % 4 == 0);
+        ctx.effect("read the parity", || {
+            let _ = *is_even.get();
+        });
+        let hook_allocations = allocations() - before;
+        HOOK_ALLOCATIONS.with(|count| count.set(count.get() + hook_allocations));
+
+        ctx.render_with_event(
+            |_: &()| set_count.mutate(|count| count.0 += 1),
+            |ctx| Native::Button {
+                label: doubled.get().0.to_string(),
+                on_click: ctx.event(()),
+            },
+        )
+    }
+}
+
+#[test]
+fn renders_keep_values_and_allocations_stable() {
+    let mut renderer = TestRenderer::new(Counter);
+    let button = renderer.find_all_by_kind(NativeKind::Button).remove(0);
+    // Lets the channel and the subscription index reach their steady size
+    // first.
+    for _ in 0..100 {
+        renderer.click(&button);
+    }
+    let live_blocks = LIVE_BLOCKS.with(Cell::get);
+    assert_eq!(LIVE_VALUES.load(Ordering::SeqCst), 2);
+    HOOK_ALLOCATIONS.with(|count| count.set(0));
+
+    for _ in 0..10_000 {
+        renderer.click(&button);
+    }
+
+    assert_eq!(LIVE_BLOCKS.with(Cell::get), live_blocks);
+    assert_eq!(HOOK_ALLOCATIONS.with(Cell::get), 0);
+    assert_eq!(LIVE_VALUES.load(Ordering::SeqCst), 2);
+    renderer.get_by_text("20200");
+
+    drop(renderer);
+    assert_eq!(LIVE_VALUES.load(Ordering::SeqCst), 0);
+}